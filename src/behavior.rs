@@ -0,0 +1,22 @@
+use crate::coin::Coin;
+
+/// How a byzantine node picks the value it sends to `receiver`.
+#[derive(Debug, Clone, Copy)]
+pub enum Behavior {
+    Silent,
+    Constant(bool),
+    Random,
+    Equivocate, // 1 to even ids, 0 to odd ones
+}
+
+impl Behavior {
+    /// The value sent to `receiver`, `None` if nothing is sent.
+    pub fn value(&self, receiver: usize, coin: &mut Coin) -> Option<bool> {
+        match self {
+            Behavior::Silent => None,
+            Behavior::Constant(value) => Some(*value),
+            Behavior::Random => Some(coin.flip()),
+            Behavior::Equivocate => Some(receiver.is_multiple_of(2)),
+        }
+    }
+}
@@ -0,0 +1,530 @@
+pub mod process;
+
+use super::{Logger, NodeState, SimulationReport, Termination};
+use crate::behavior::Behavior;
+use crate::coin::Coin;
+use crate::network::{Kind, Link, Network, NetworkConfig, Packet, Recording};
+use crate::trace::{Actor, Field, Traceable};
+use process::Process;
+use rand::SeedableRng;
+use rand::{self, rngs::StdRng, Rng};
+use std::{cell::RefCell, fmt::Debug, rc::Rc};
+
+type Round = usize;
+type LinkInterface = Rc<RefCell<Link<Message>>>;
+
+#[derive(Clone, Copy)]
+pub enum Message {
+    Report(Round, bool),
+    Proposal(Round, Option<bool>),
+}
+
+impl Debug for Message {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Report(round, value) => write!(f, "Report({round}, {})", *value as u8),
+            Self::Proposal(round, Some(value)) => {
+                write!(f, "Proposal({round}, {})", *value as u8)
+            }
+            Self::Proposal(round, None) => write!(f, "Proposal({round}, ⊥)"),
+        }
+    }
+}
+
+impl Kind for Message {
+    fn kind(&self) -> &'static str {
+        match self {
+            Self::Report(..) => "Report",
+            Self::Proposal(..) => "Proposal",
+        }
+    }
+}
+
+/// `Crash` stops a node in the given simulation round, `Byzantine` nodes
+/// follow the rounds of the protocol but send values picked by `Behavior`.
+#[derive(Debug, Clone, Copy)]
+pub enum Fault {
+    Crash(usize),
+    Byzantine(Behavior),
+}
+
+/// Thresholds of the protocol. Crash tolerant runs assume up to
+/// `(n - 1) / 2` faulty nodes, byzantine ones up to `(n - 1) / 5`.
+#[derive(Debug, Clone, Copy)]
+struct Thresholds {
+    node_count: usize,
+    max_faults: usize,
+    byzantine: bool,
+}
+
+impl Thresholds {
+    fn new(node_count: usize, byzantine: bool) -> Self {
+        let max_faults = match byzantine {
+            true => (node_count.max(1) - 1) / 5,
+            false => (node_count.max(1) - 1) / 2,
+        };
+        Thresholds {
+            node_count,
+            max_faults,
+            byzantine,
+        }
+    }
+
+    /// Number of messages to wait for in every phase.
+    fn quorum(&self) -> usize {
+        self.node_count - self.max_faults
+    }
+
+    fn propose(&self, count: usize) -> bool {
+        match self.byzantine {
+            true => 2 * count > self.node_count + self.max_faults,
+            false => 2 * count > self.node_count,
+        }
+    }
+
+    fn decide(&self, count: usize) -> bool {
+        match self.byzantine {
+            true => 2 * count > self.node_count + self.max_faults,
+            false => count > self.max_faults,
+        }
+    }
+
+    fn adopt(&self, count: usize) -> bool {
+        match self.byzantine {
+            true => count > self.max_faults,
+            false => count > 0,
+        }
+    }
+}
+
+enum Action {
+    Store(String, String),
+    Send(usize, usize, Message), // receiver, packet id, message
+    Receive(Packet<Message>),
+    Check(String, String, bool),
+    Coin(bool),
+    Decide(bool),
+    Crash,
+}
+
+impl Debug for Action {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Store(var, value) => write!(f, "store {var} = {value}"),
+            Self::Send(receiver_id, _, message) => write!(f, "send {message:?} to {receiver_id}"),
+            Self::Receive(packet) => {
+                write!(f, "received {:?} from {}", packet.content, packet.sender)
+            }
+            Self::Check(condition, values, result) => {
+                write!(f, "check {condition}: {values} => {result}")
+            }
+            Self::Coin(value) => write!(f, "flips coin {}", *value as u8),
+            Self::Decide(value) => write!(f, "decides for {}", *value as u8),
+            Self::Crash => write!(f, "crashes"),
+        }
+    }
+}
+
+impl Traceable for Action {
+    fn action(&self) -> &'static str {
+        match self {
+            Self::Store(..) => "store",
+            Self::Send(..) => "send",
+            Self::Receive(..) => "receive",
+            Self::Check(..) => "check",
+            Self::Coin(..) => "coin",
+            Self::Decide(..) => "decide",
+            Self::Crash => "crash",
+        }
+    }
+
+    fn fields(&self) -> Vec<(&'static str, Field)> {
+        match self {
+            Self::Store(var, value) => vec![
+                ("variable", var.as_str().into()),
+                ("value", value.as_str().into()),
+            ],
+            Self::Send(receiver, id, message) => vec![
+                ("receiver", (*receiver).into()),
+                ("packet", (*id).into()),
+                ("message", Field::debug(message)),
+            ],
+            Self::Receive(packet) => vec![
+                ("sender", packet.sender.into()),
+                ("packet", packet.id.into()),
+                ("clock", Field::Clock(packet.clock.clone())),
+                ("message", Field::debug(&packet.content)),
+            ],
+            Self::Check(condition, values, result) => vec![
+                ("condition", condition.as_str().into()),
+                ("values", values.as_str().into()),
+                ("result", (*result).into()),
+            ],
+            Self::Coin(value) | Self::Decide(value) => vec![("value", (*value).into())],
+            Self::Crash => Vec::new(),
+        }
+    }
+}
+
+/// Ben-Or randomized binary consensus on an asynchronous network.
+pub struct System {
+    nodes: Vec<Process>,
+    network: Network<Message>,
+    crashes: Vec<Option<usize>>,
+    decision_rounds: Vec<Option<usize>>,
+}
+
+impl crate::System for System {
+    /// The `server_count` crashing nodes take the highest ids and crash
+    /// within 50 rounds.
+    fn new_rand(node_count: usize, server_count: usize, seed: Option<u64>) -> Self {
+        let config = NetworkConfig::new(true, 10);
+        Self::new_with_network(node_count, server_count, seed, config)
+    }
+
+    fn new_with_network(
+        node_count: usize,
+        server_count: usize,
+        seed: Option<u64>,
+        config: NetworkConfig,
+    ) -> Self {
+        let mut rng = match seed {
+            Some(seed) => StdRng::seed_from_u64(seed),
+            None => StdRng::from_entropy(),
+        };
+        let crashes = server_count.min(Thresholds::new(node_count, false).max_faults);
+        let faults = (node_count - crashes..node_count)
+            .map(|id| (id, Fault::Crash(rng.gen_range(0..50))))
+            .collect();
+        Self::new_rand_with(node_count, faults, Some(rng.gen()), config)
+    }
+
+    fn from_recording(
+        node_count: usize,
+        _server_count: usize,
+        config: NetworkConfig,
+        recording: Recording,
+    ) -> Result<Self, String> {
+        let inputs = (0..node_count)
+            .map(|id| recording.input(id))
+            .collect::<Result<_, _>>()?;
+        let faults = recording
+            .crashes
+            .iter()
+            .map(|(id, round)| (*id, Fault::Crash(*round)))
+            .collect();
+        let mut system = Self::new(inputs, faults, &mut StdRng::seed_from_u64(0), config);
+        system.replay(recording);
+        Ok(system)
+    }
+
+    /// The `(n - 1) / 2` crashing nodes Ben-Or tolerates.
+    fn max_servers(node_count: usize) -> usize {
+        Thresholds::new(node_count, false).max_faults
+    }
+
+    fn simulate_with(
+        &mut self,
+        max_rounds: Option<usize>,
+        logger: &mut Logger,
+    ) -> SimulationReport {
+        let max_rounds = match max_rounds {
+            Some(nr) => nr,
+            None => usize::MAX,
+        };
+
+        while !self.decided() && self.network.round() < max_rounds {
+            self.step_with(logger);
+        }
+
+        let termination = match self.decided() {
+            true => Termination::Decided,
+            false => Termination::MaxRounds,
+        };
+        let rounds = self.network.round();
+        let mut report = SimulationReport::new(&self.network, rounds, termination);
+        let mut decided: Vec<_> = self.correct_decisions().into_iter().flatten().collect();
+        decided.sort();
+        decided.dedup();
+        report.decided = decided.iter().map(|d| format!("{d:?}")).collect();
+        report.decision_rounds = self.decision_rounds.clone();
+        report
+    }
+
+    fn step_with(&mut self, logger: &mut Logger) {
+        let cur_round = self.network.round();
+        logger.log_round(cur_round);
+        self.network.exchange_messages();
+        if !self.network.events().is_empty() {
+            logger.log_actor(&self.network);
+            for event in self.network.events() {
+                logger.log_action(event);
+            }
+        }
+        for (id, node) in self.nodes.iter_mut().enumerate() {
+            match self.crashes[id] {
+                Some(round) if round <= cur_round => {
+                    if round == cur_round {
+                        logger.log_actor(node);
+                        logger.log_action(&Action::Crash);
+                    }
+                    self.network.get_link_ref(id).borrow_mut().empty_buffer();
+                }
+                _ => node.exec(logger),
+            }
+        }
+        for (id, node) in self.nodes.iter().enumerate() {
+            if self.decision_rounds[id].is_none() && node.get_decision().is_some() {
+                self.decision_rounds[id] = Some(cur_round);
+            }
+        }
+    }
+
+    fn node_states(&self) -> Vec<NodeState> {
+        let round = self.network.round();
+        self.nodes
+            .iter()
+            .enumerate()
+            .map(|(id, node)| {
+                let crashed = self.crashes[id].is_some_and(|crash| crash < round);
+                NodeState::new(node, crashed, node.state())
+            })
+            .collect()
+    }
+
+    fn decided(&self) -> bool {
+        (0..self.nodes.len())
+            .filter(|&id| self.is_correct(id))
+            .all(|id| self.nodes[id].get_decision().is_some())
+    }
+
+    fn record(&mut self) {
+        self.network.record()
+    }
+
+    fn recording(&self) -> Option<Recording> {
+        let mut recording = self.network.recording()?.clone();
+        for (id, node) in self.nodes.iter().enumerate() {
+            recording.inputs.push((id, node.get_input().to_string()));
+            if let Some(round) = self.crashes[id] {
+                recording.crashes.insert(id, round);
+            }
+            if !node.coin().flips().is_empty() {
+                recording.coins.insert(id, node.coin().flips().to_vec());
+            }
+        }
+        Some(recording)
+    }
+
+    fn replay(&mut self, recording: Recording) {
+        for (id, node) in self.nodes.iter_mut().enumerate() {
+            if let Some(flips) = recording.coins.get(&id) {
+                node.coin_mut().replay(flips.clone());
+            }
+        }
+        self.network.replay(recording)
+    }
+}
+
+impl System {
+    /// Draws a random input for every node.
+    pub fn new_rand_with(
+        node_count: usize,
+        faults: Vec<(usize, Fault)>,
+        seed: Option<u64>,
+        config: NetworkConfig,
+    ) -> Self {
+        let mut rng = match seed {
+            Some(seed) => StdRng::seed_from_u64(seed),
+            None => StdRng::from_entropy(),
+        };
+        let inputs = (0..node_count).map(|_| rng.gen::<bool>()).collect();
+        Self::new(inputs, faults, &mut rng, config)
+    }
+
+    /// Byzantine thresholds are used as soon as any fault is byzantine.
+    pub fn new(
+        inputs: Vec<bool>,
+        faults: Vec<(usize, Fault)>,
+        rng: &mut StdRng,
+        config: NetworkConfig,
+    ) -> Self {
+        let node_count = inputs.len();
+        let byzantine = faults
+            .iter()
+            .any(|(_, fault)| matches!(fault, Fault::Byzantine(_)));
+        let thresholds = Thresholds::new(node_count, byzantine);
+        let network = Network::with_config(node_count, Some(rng.gen()), config);
+
+        let mut nodes = Vec::new();
+        let mut crashes = vec![None; node_count];
+        for (id, input) in inputs.into_iter().enumerate() {
+            let fault = faults.iter().find(|(f, _)| *f == id).map(|(_, f)| *f);
+            let behavior = match fault {
+                Some(Fault::Byzantine(behavior)) => Some(behavior),
+                Some(Fault::Crash(round)) => {
+                    crashes[id] = Some(round);
+                    None
+                }
+                None => None,
+            };
+            nodes.push(Process::new(
+                id,
+                network.get_link_ref(id),
+                thresholds,
+                input,
+                behavior,
+                StdRng::seed_from_u64(rng.gen()),
+            ));
+        }
+
+        System {
+            decision_rounds: vec![None; nodes.len()],
+            nodes,
+            network,
+            crashes,
+        }
+    }
+
+    /// Correct nodes neither crash nor behave byzantine.
+    pub fn is_correct(&self, id: usize) -> bool {
+        self.crashes[id].is_none() && self.nodes[id].is_honest()
+    }
+
+    pub fn correct_inputs(&self) -> Vec<bool> {
+        (0..self.nodes.len())
+            .filter(|&id| self.is_correct(id))
+            .map(|id| self.nodes[id].get_input())
+            .collect()
+    }
+
+    pub fn correct_decisions(&self) -> Vec<Option<bool>> {
+        (0..self.nodes.len())
+            .filter(|&id| self.is_correct(id))
+            .map(|id| self.nodes[id].get_decision())
+            .collect()
+    }
+
+    /// Highest protocol round any correct node reached.
+    pub fn max_round(&self) -> Round {
+        (0..self.nodes.len())
+            .filter(|&id| self.is_correct(id))
+            .map(|id| self.nodes[id].get_round())
+            .max()
+            .unwrap_or(0)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::Fault;
+    use crate::behavior::Behavior;
+    use crate::network::NetworkConfig;
+    use crate::System;
+    use crate::*;
+    use rand::{rngs::StdRng, SeedableRng};
+
+    fn assert_agreement(system: &ben_or::System) {
+        let decisions = system.correct_decisions();
+        assert!(decisions.iter().all(|d| d.is_some()));
+        assert!(decisions.iter().all(|d| *d == decisions[0]));
+    }
+
+    #[test]
+    fn ben_or_rng_is_deterministic() {
+        let system1: ben_or::System = System::new_rand(5, 2, Some(42));
+        let system2: ben_or::System = System::new_rand(5, 2, Some(42));
+        assert_eq!(system1.correct_inputs(), system2.correct_inputs());
+    }
+
+    #[test]
+    fn ben_or_limits_crashing_nodes() {
+        let system: ben_or::System = System::new_rand(3, 5, Some(42));
+        assert_eq!(system.correct_inputs().len(), 2);
+    }
+
+    #[test]
+    fn ben_or_terminates_with_crashes() {
+        for seed in 0..100 {
+            let mut system: ben_or::System = System::new_rand(5, 2, Some(seed));
+            system.simulate(Some(20_000), Some("ben_or_crash")).unwrap();
+            assert!(system.decided());
+            assert!(system.max_round() < 100);
+            assert_agreement(&system);
+        }
+    }
+
+    #[test]
+    fn ben_or_terminates_with_byzantine_nodes() {
+        let behaviors = [
+            Behavior::Silent,
+            Behavior::Constant(true),
+            Behavior::Random,
+            Behavior::Equivocate,
+        ];
+        for seed in 0..100 {
+            let faults = vec![(0, Fault::Byzantine(behaviors[seed as usize % 4]))];
+            let config = NetworkConfig::new(true, 10);
+            let mut system = ben_or::System::new_rand_with(6, faults, Some(seed), config);
+            system
+                .simulate(Some(20_000), Some("ben_or_byzantine"))
+                .unwrap();
+            assert!(system.decided());
+            assert!(system.max_round() < 100);
+            assert_agreement(&system);
+        }
+    }
+
+    #[test]
+    fn ben_or_is_valid() {
+        let mut rng = StdRng::seed_from_u64(7);
+        for input in [false, true] {
+            let faults = vec![(1, Fault::Crash(3)), (3, Fault::Crash(0))];
+            let config = NetworkConfig::new(true, 10);
+            let mut system = ben_or::System::new(vec![input; 5], faults, &mut rng, config);
+            let report = system.simulate(Some(20_000), None).unwrap();
+            assert_eq!(report.termination, Termination::Decided);
+            assert_eq!(report.decided, vec![format!("{input:?}")]);
+            assert_eq!(report.message_counts.len(), 2);
+            assert!(report.message_counts["Report"] >= report.message_counts["Proposal"]);
+            assert_agreement(&system);
+            assert_eq!(system.correct_decisions()[0], Some(input));
+
+            let faults = vec![(2, Fault::Byzantine(Behavior::Constant(!input)))];
+            let config = NetworkConfig::new(true, 10);
+            let mut system = ben_or::System::new(vec![input; 6], faults, &mut rng, config);
+            system.simulate(Some(20_000), None).unwrap();
+            assert_agreement(&system);
+            assert_eq!(system.correct_decisions()[0], Some(input));
+        }
+    }
+
+    #[test]
+    fn ben_or_replays_from_recording() {
+        use crate::network::Recording;
+        use crate::sink::MemorySink;
+        use crate::trace::{first_difference, TraceFormat};
+
+        let trace = |system: &mut ben_or::System| {
+            let sink = MemorySink::new();
+            let mut logger = Logger::with_sink(Box::new(sink.clone()), TraceFormat::Text);
+            system.simulate_with(Some(20_000), &mut logger);
+            sink.contents()
+        };
+        let mut flipped = false;
+        for seed in 0..10 {
+            let config = NetworkConfig::new(true, 10);
+            let mut system = ben_or::System::new_with_network(7, 3, Some(seed), config.clone());
+            system.record();
+            let recorded = trace(&mut system);
+            let recording = system.recording().unwrap();
+            assert_eq!(recording.crashes.len(), 3);
+            flipped |= !recording.coins.is_empty();
+
+            let recording = Recording::parse(&recording.to_text()).unwrap();
+            let mut system = ben_or::System::from_recording(7, 3, config, recording).unwrap();
+            assert_eq!(first_difference(&recorded, &trace(&mut system)), None);
+        }
+        assert!(flipped);
+    }
+}
@@ -0,0 +1,244 @@
+use super::*;
+use std::collections::HashSet;
+
+#[derive(Clone, Copy, PartialEq)]
+enum Phase {
+    Report,
+    WaitReports,
+    WaitProposals,
+}
+
+/// A node running Ben-Or. With a `behavior` it is byzantine: it keeps pace
+/// with the protocol but sends whatever the behavior dictates.
+pub struct Process {
+    id: usize,
+    link: LinkInterface,
+    thresholds: Thresholds,
+    input: bool,
+    value: bool,
+    round: Round,
+    phase: Phase,
+    decision: Option<bool>,
+    behavior: Option<Behavior>,
+    coin: Coin,
+    inbox: Vec<Packet<Message>>,
+}
+
+impl Debug for Process {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.behavior {
+            Some(_) => write!(f, "Byzantine #{}", self.id),
+            None => write!(f, "Process #{}", self.id),
+        }
+    }
+}
+
+impl Actor for Process {
+    fn actor_kind(&self) -> &'static str {
+        match self.behavior {
+            Some(_) => "Byzantine",
+            None => "Process",
+        }
+    }
+
+    fn actor_id(&self) -> Option<usize> {
+        Some(self.id)
+    }
+}
+
+impl Process {
+    pub(super) fn new(
+        id: usize,
+        link: LinkInterface,
+        thresholds: Thresholds,
+        input: bool,
+        behavior: Option<Behavior>,
+        rng: StdRng,
+    ) -> Self {
+        Process {
+            id,
+            link,
+            thresholds,
+            input,
+            value: input,
+            round: 0,
+            phase: Phase::Report,
+            decision: None,
+            behavior,
+            coin: Coin::new(rng),
+            inbox: Vec::new(),
+        }
+    }
+
+    pub fn exec(&mut self, logger: &mut Logger) {
+        logger.log_actor(self);
+        self.inbox.extend(self.link.borrow_mut().empty_buffer());
+
+        // Asynchrony lets several phases complete within one round.
+        loop {
+            match self.phase {
+                Phase::Report => {
+                    self.broadcast(Message::Report(self.round, self.value), logger);
+                    self.phase = Phase::WaitReports;
+                }
+                Phase::WaitReports => {
+                    let Some(reports) = self.quorum(false, logger) else {
+                        break;
+                    };
+                    let ones = reports.iter().filter(|v| **v == Some(true)).count();
+                    let zeros = reports.len() - ones;
+                    let proposal = match (
+                        self.thresholds.propose(ones),
+                        self.thresholds.propose(zeros),
+                    ) {
+                        (true, _) => Some(true),
+                        (_, true) => Some(false),
+                        _ => None,
+                    };
+                    logger.log_action(&Action::Check(
+                        String::from("#reports of one value > threshold"),
+                        format!("{ones} ones, {zeros} zeros"),
+                        proposal.is_some(),
+                    ));
+                    self.broadcast(Message::Proposal(self.round, proposal), logger);
+                    self.phase = Phase::WaitProposals;
+                }
+                Phase::WaitProposals => {
+                    let Some(proposals) = self.quorum(true, logger) else {
+                        break;
+                    };
+                    self.evaluate(&proposals, logger);
+                    self.round += 1;
+                    self.phase = Phase::Report;
+                    let round = self.round;
+                    self.inbox.retain(|p| match p.content {
+                        Message::Report(r, _) | Message::Proposal(r, _) => r >= round,
+                    });
+                }
+            }
+        }
+    }
+
+    pub fn is_honest(&self) -> bool {
+        self.behavior.is_none()
+    }
+
+    pub fn get_input(&self) -> bool {
+        self.input
+    }
+
+    pub fn get_decision(&self) -> Option<bool> {
+        self.decision
+    }
+
+    pub fn get_round(&self) -> Round {
+        self.round
+    }
+
+    /// Coin flips and random byzantine values drawn so far.
+    pub fn coin(&self) -> &Coin {
+        &self.coin
+    }
+
+    pub fn coin_mut(&mut self) -> &mut Coin {
+        &mut self.coin
+    }
+
+    /// Current values of the node's variables.
+    pub fn state(&self) -> Vec<(String, String)> {
+        let phase = match self.phase {
+            Phase::Report => "report",
+            Phase::WaitReports => "wait for reports",
+            Phase::WaitProposals => "wait for proposals",
+        };
+        let decision = self
+            .decision
+            .map_or(String::from("⊥"), |d| (d as u8).to_string());
+        vec![
+            (String::from("round"), self.round.to_string()),
+            (String::from("phase"), String::from(phase)),
+            (String::from("x"), (self.value as u8).to_string()),
+            (String::from("decision"), decision),
+        ]
+    }
+
+    /// Values of the first message per sender in the current round, once
+    /// enough senders have been heard from.
+    fn quorum(&self, proposals: bool, logger: &mut Logger) -> Option<Vec<Option<bool>>> {
+        let mut senders = HashSet::new();
+        let mut values = Vec::new();
+        for packet in self.inbox.iter() {
+            let value = match packet.content {
+                Message::Report(r, v) if !proposals && r == self.round => Some(v),
+                Message::Proposal(r, v) if proposals && r == self.round => v,
+                _ => continue,
+            };
+            if senders.insert(packet.sender) {
+                values.push((packet, value));
+            }
+        }
+        if values.len() < self.thresholds.quorum() {
+            return None;
+        }
+        values.truncate(self.thresholds.quorum());
+        for (packet, _) in values.iter() {
+            self.link.borrow_mut().receive(packet);
+            logger.log_action(&Action::Receive((*packet).clone()));
+        }
+        Some(values.into_iter().map(|(_, v)| v).collect())
+    }
+
+    fn evaluate(&mut self, proposals: &[Option<bool>], logger: &mut Logger) {
+        let ones = proposals.iter().filter(|v| **v == Some(true)).count();
+        let zeros = proposals.iter().filter(|v| **v == Some(false)).count();
+        let (value, count) = if ones >= zeros {
+            (true, ones)
+        } else {
+            (false, zeros)
+        };
+
+        let decide = self.thresholds.decide(count);
+        logger.log_action(&Action::Check(
+            String::from("#proposals of one value > decide threshold"),
+            format!("{ones} ones, {zeros} zeros"),
+            decide,
+        ));
+        if decide && self.decision.is_none() {
+            logger.log_action(&Action::Decide(value));
+            self.decision = Some(value);
+        }
+
+        self.value = if self.thresholds.adopt(count) {
+            value
+        } else {
+            let coin = self.coin.flip();
+            logger.log_action(&Action::Coin(coin));
+            coin
+        };
+        logger.log_action(&Action::Store(
+            String::from("x"),
+            format!("{}", self.value as u8),
+        ));
+    }
+
+    /// Sends `message` to every node. Byzantine nodes replace its value
+    /// with whatever their behavior picks for the receiver.
+    fn broadcast(&mut self, message: Message, logger: &mut Logger) {
+        for receiver in 0..self.thresholds.node_count {
+            let value = match self.behavior {
+                None => None,
+                Some(behavior) => match behavior.value(receiver, &mut self.coin) {
+                    None => return,
+                    value => value,
+                },
+            };
+            let message = match (message, value) {
+                (Message::Report(round, _), Some(value)) => Message::Report(round, value),
+                (Message::Proposal(round, _), Some(value)) => Message::Proposal(round, Some(value)),
+                (message, None) => message,
+            };
+            let id = self.link.borrow_mut().enqueue(receiver, message);
+            logger.log_action(&Action::Send(receiver, id, message));
+        }
+    }
+}
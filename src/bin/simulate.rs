@@ -0,0 +1,462 @@
+use distributed_algorithms_demo::diagram::{self, Filter};
+use distributed_algorithms_demo::experiment::{self, Sweep};
+use distributed_algorithms_demo::network::{NetworkConfig, Recording};
+use distributed_algorithms_demo::sink::{MemorySink, StdoutSink};
+use distributed_algorithms_demo::trace::{self, TraceEvent, TraceFormat};
+use distributed_algorithms_demo::{ben_or, king, paxos, shiviz};
+use distributed_algorithms_demo::{Logger, SimulationReport, System};
+use std::process::ExitCode;
+use std::{env, fs};
+
+const USAGE: &str = "\
+Usage: simulate <command> [options]
+
+Commands:
+  run                 simulate a single configuration and print a summary
+  sweep               simulate every combination of the given lists and seeds
+  replay <trace>      simulate again and compare against a trace written by run
+
+Options:
+  --algorithm NAME    paxos, king or ben-or (default paxos)
+  --nodes N[,N..]     number of nodes (default 3)
+  --servers N[,N..]   servers, byzantine or crashing nodes (default 1), at most
+                      the faults king and ben-or tolerate
+  --seed N            seed of run and replay, drawn for run if not given
+  --seeds A..B|N,..   seeds of sweep (default 0..10)
+  --max-rounds N      stop after N rounds (default 10000)
+  --sync | --async    network timing (default async)
+  --latency N[,N..]   maximum latency in rounds (default 10)
+  --drop P[,P..]      packet drop probability (default 0)
+  --log PATH          write the trace of run to PATH, - for stdout
+  --trace text|jsonl  format of the trace (default text)
+  --record PATH       write the decisions of the network and the nodes' random
+                      choices during run to PATH
+  --recording PATH    replay the decisions in PATH instead of a seed
+  --diagram PATH      write the messages of run as a sequence diagram, Mermaid
+                      for .mmd, PlantUML for .puml or a space-time diagram for .svg
+  --diagram-nodes N,..
+                      only messages between these nodes in the diagram
+  --diagram-rounds A..B
+                      only messages sent in these rounds in the diagram
+  --shiviz PATH       write sends and receives with vector clocks for ShiViz
+  --threads N         worker threads of sweep (default one per core)
+  --format csv|json   output format of sweep (default csv)
+  --output PATH       write the sweep statistics to PATH instead of stdout";
+
+/// Writes the events of a run as a diagram.
+type Export = fn(&[TraceEvent], &Filter) -> String;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Algorithm {
+    Paxos,
+    King,
+    BenOr,
+}
+
+#[derive(Debug, PartialEq)]
+enum Command {
+    Run,
+    Sweep,
+    Replay(String),
+}
+
+#[derive(Debug, PartialEq)]
+struct Options {
+    algorithm: Algorithm,
+    nodes: Vec<usize>,
+    servers: Vec<usize>,
+    seed: Option<u64>,
+    seeds: Vec<u64>,
+    max_rounds: usize,
+    asynchronous: bool,
+    latencies: Vec<usize>,
+    drops: Vec<f64>,
+    log: Option<String>,
+    trace_format: TraceFormat,
+    record: Option<String>,
+    recording: Option<String>,
+    diagram: Option<String>,
+    diagram_filter: Filter,
+    shiviz: Option<String>,
+    threads: Option<usize>,
+    json: bool,
+    output: Option<String>,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Options {
+            algorithm: Algorithm::Paxos,
+            nodes: vec![3],
+            servers: vec![1],
+            seed: None,
+            seeds: (0..10).collect(),
+            max_rounds: 10_000,
+            asynchronous: true,
+            latencies: vec![10],
+            drops: vec![0.0],
+            log: None,
+            trace_format: TraceFormat::Text,
+            record: None,
+            recording: None,
+            diagram: None,
+            diagram_filter: Filter::default(),
+            shiviz: None,
+            threads: None,
+            json: false,
+            output: None,
+        }
+    }
+}
+
+fn main() -> ExitCode {
+    let args: Vec<String> = env::args().skip(1).collect();
+    let (command, options) = match parse(&args) {
+        Ok(parsed) => parsed,
+        Err(e) => {
+            eprintln!("{e}\n\n{USAGE}");
+            return ExitCode::from(2);
+        }
+    };
+    let result = match options.algorithm {
+        Algorithm::Paxos => execute::<paxos::System>(&command, &options),
+        Algorithm::King => execute::<king::System>(&command, &options),
+        Algorithm::BenOr => execute::<ben_or::System>(&command, &options),
+    };
+    match result {
+        Ok(code) => code,
+        Err(e) => {
+            eprintln!("{e}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn parse(args: &[String]) -> Result<(Command, Options), String> {
+    let mut args = args.iter();
+    let command = match args.next().map(String::as_str) {
+        Some("run") => Command::Run,
+        Some("sweep") => Command::Sweep,
+        Some("replay") => match args.next() {
+            Some(trace) => Command::Replay(trace.clone()),
+            None => return Err(String::from("replay needs the path of a trace")),
+        },
+        Some(other) => return Err(format!("Unknown command {other}")),
+        None => return Err(String::from("Missing command")),
+    };
+
+    let mut options = Options::default();
+    while let Some(flag) = args.next() {
+        let mut value = || {
+            args.next()
+                .map(String::as_str)
+                .ok_or(format!("{flag} needs a value"))
+        };
+        match flag.as_str() {
+            "--algorithm" => {
+                options.algorithm = match value()? {
+                    "paxos" => Algorithm::Paxos,
+                    "king" => Algorithm::King,
+                    "ben-or" => Algorithm::BenOr,
+                    other => return Err(format!("Unknown algorithm {other}")),
+                }
+            }
+            "--nodes" => options.nodes = parse_list(value()?)?,
+            "--servers" => options.servers = parse_list(value()?)?,
+            "--seed" => options.seed = Some(parse_value(value()?)?),
+            "--seeds" => options.seeds = parse_seeds(value()?)?,
+            "--max-rounds" => options.max_rounds = parse_value(value()?)?,
+            "--sync" => options.asynchronous = false,
+            "--async" => options.asynchronous = true,
+            "--latency" => options.latencies = parse_list(value()?)?,
+            "--drop" => options.drops = parse_list(value()?)?,
+            "--log" => options.log = Some(String::from(value()?)),
+            "--trace" => {
+                options.trace_format = match value()? {
+                    "text" => TraceFormat::Text,
+                    "jsonl" => TraceFormat::JsonLines,
+                    other => return Err(format!("Unknown trace format {other}")),
+                }
+            }
+            "--record" => options.record = Some(String::from(value()?)),
+            "--recording" => options.recording = Some(String::from(value()?)),
+            "--diagram" => {
+                let path = value()?;
+                diagram_format(path)?;
+                options.diagram = Some(String::from(path))
+            }
+            "--shiviz" => options.shiviz = Some(String::from(value()?)),
+            "--diagram-nodes" => {
+                let nodes = parse_list(value()?)?;
+                options.diagram_filter.nodes = Some(nodes.into_iter().collect())
+            }
+            "--diagram-rounds" => {
+                let value = value()?;
+                let (start, end) = value
+                    .split_once("..")
+                    .ok_or(format!("Expected a range A..B instead of {value}"))?;
+                options.diagram_filter.rounds = Some(parse_value(start)?..parse_value(end)?)
+            }
+            "--threads" => options.threads = Some(parse_value(value()?)?),
+            "--format" => {
+                options.json = match value()? {
+                    "csv" => false,
+                    "json" => true,
+                    other => return Err(format!("Unknown format {other}")),
+                }
+            }
+            "--output" => options.output = Some(String::from(value()?)),
+            other => return Err(format!("Unknown option {other}")),
+        }
+    }
+    Ok((command, options))
+}
+
+fn parse_value<T: std::str::FromStr>(value: &str) -> Result<T, String> {
+    value
+        .trim()
+        .parse()
+        .map_err(|_| format!("Invalid value {value}"))
+}
+
+fn parse_list<T: std::str::FromStr>(value: &str) -> Result<Vec<T>, String> {
+    value.split(',').map(parse_value).collect()
+}
+
+/// The exporter for the extension of `path`.
+fn diagram_format(path: &str) -> Result<Export, String> {
+    match path.rsplit_once('.').map(|(_, extension)| extension) {
+        Some("mmd") => Ok(diagram::to_mermaid),
+        Some("puml") => Ok(diagram::to_plantuml),
+        Some("svg") => Ok(diagram::to_svg),
+        _ => Err(format!("{path} is neither .mmd, .puml nor .svg")),
+    }
+}
+
+/// Either a range `A..B` or a list.
+fn parse_seeds(value: &str) -> Result<Vec<u64>, String> {
+    match value.split_once("..") {
+        Some((start, end)) => Ok((parse_value(start)?..parse_value(end)?).collect()),
+        None => parse_list(value),
+    }
+}
+
+fn execute<S: System>(command: &Command, options: &Options) -> Result<ExitCode, String> {
+    match command {
+        Command::Run => {
+            let seed = options.seed.unwrap_or_else(rand::random);
+            let mut logger = match options.log.as_deref() {
+                Some("-") => Logger::with_sink(Box::new(StdoutSink), options.trace_format),
+                Some(path) => Logger::with_format(path, options.trace_format)
+                    .map_err(|e| format!("{path}: {e}"))?,
+                None => Logger::new(None),
+            };
+            if options.diagram.is_some() || options.shiviz.is_some() {
+                logger.capture();
+            }
+            let (report, recording) = simulate::<S>(options, Some(seed), &mut logger, None)?;
+            logger
+                .flush()
+                .map_err(|e| format!("Couldn't write the log: {e}"))?;
+            if let Some(path) = &options.record {
+                fs::write(path, recording.to_text()).map_err(|e| format!("{path}: {e}"))?;
+            }
+            let events = logger.take_events();
+            if let Some(path) = &options.diagram {
+                let export = diagram_format(path)?;
+                let diagram = export(&events, &options.diagram_filter);
+                fs::write(path, diagram).map_err(|e| format!("{path}: {e}"))?;
+            }
+            if let Some(path) = &options.shiviz {
+                let log = shiviz::to_shiviz(&events);
+                fs::write(path, log).map_err(|e| format!("{path}: {e}"))?;
+            }
+            println!("seed: {seed}");
+            print_report(&report);
+            Ok(ExitCode::SUCCESS)
+        }
+        Command::Sweep => {
+            for &nodes in &options.nodes {
+                for &servers in &options.servers {
+                    check_servers::<S>(nodes, servers)?;
+                }
+            }
+            let mut sweep = Sweep::new(
+                options.nodes.clone(),
+                options.servers.clone(),
+                options.seeds.clone(),
+            );
+            sweep.max_latencies = options.latencies.clone();
+            sweep.drop_probabilities = options.drops.clone();
+            sweep.asynchronous = options.asynchronous;
+            sweep.max_rounds = Some(options.max_rounds);
+            if let Some(threads) = options.threads {
+                sweep.threads = threads;
+            }
+            let statistics = sweep.run::<S>();
+            let output = match options.json {
+                true => experiment::to_json(&statistics),
+                false => experiment::to_csv(&statistics),
+            };
+            match &options.output {
+                Some(path) => fs::write(path, output).map_err(|e| format!("{path}: {e}"))?,
+                None => print!("{output}"),
+            }
+            Ok(ExitCode::SUCCESS)
+        }
+        Command::Replay(trace) => {
+            if options.seed.is_none() && options.recording.is_none() {
+                return Err(String::from(
+                    "replay needs the --seed or --recording of the run",
+                ));
+            }
+            let recorded = fs::read_to_string(trace).map_err(|e| format!("{trace}: {e}"))?;
+            let recording = match &options.recording {
+                Some(path) => {
+                    let text = fs::read_to_string(path).map_err(|e| format!("{path}: {e}"))?;
+                    Some(Recording::parse(&text).map_err(|e| format!("{path}: {e}"))?)
+                }
+                None => None,
+            };
+            let sink = MemorySink::new();
+            let mut logger = Logger::with_sink(Box::new(sink.clone()), options.trace_format);
+            simulate::<S>(options, options.seed, &mut logger, recording)?;
+            let replayed = sink.contents();
+            match trace::first_difference(&recorded, &replayed) {
+                None => {
+                    println!("replay of {trace} is identical");
+                    Ok(ExitCode::SUCCESS)
+                }
+                Some((line, expected, actual)) => {
+                    println!("replay diverges at line {line}");
+                    println!("recorded: {}", expected.unwrap_or("<end of trace>"));
+                    println!("replayed: {}", actual.unwrap_or("<end of trace>"));
+                    Ok(ExitCode::FAILURE)
+                }
+            }
+        }
+    }
+}
+
+/// `run` and `replay` take a single value of every list. The decisions of
+/// the run are recorded, or replayed from `replay` instead of the seed.
+fn simulate<S: System>(
+    options: &Options,
+    seed: Option<u64>,
+    logger: &mut Logger,
+    replay: Option<Recording>,
+) -> Result<(SimulationReport, Recording), String> {
+    let single = |name: &str, len: usize| match len {
+        1 => Ok(()),
+        _ => Err(format!("--{name} takes a single value outside of sweep")),
+    };
+    single("nodes", options.nodes.len())?;
+    single("servers", options.servers.len())?;
+    single("latency", options.latencies.len())?;
+    single("drop", options.drops.len())?;
+    check_servers::<S>(options.nodes[0], options.servers[0])?;
+
+    let mut config = NetworkConfig::new(options.asynchronous, options.latencies[0]);
+    config.drop_probability = options.drops[0];
+    if !(0.0..=1.0).contains(&config.drop_probability) {
+        return Err(format!("Invalid probability {}", config.drop_probability));
+    }
+    let (nodes, servers) = (options.nodes[0], options.servers[0]);
+    let mut system = match replay {
+        Some(recording) => S::from_recording(nodes, servers, config, recording)?,
+        None => S::new_with_network(nodes, servers, seed, config),
+    };
+    system.record();
+    let report = system.simulate_with(Some(options.max_rounds), logger);
+    let recording = system.recording().unwrap_or_default();
+    Ok((report, recording))
+}
+
+/// Rejects server counts the constructors of `S` would clamp.
+fn check_servers<S: System>(nodes: usize, servers: usize) -> Result<(), String> {
+    let max = S::max_servers(nodes);
+    match servers <= max {
+        true => Ok(()),
+        false => Err(format!(
+            "--servers {servers} is more than the {max} the algorithm takes with {nodes} nodes"
+        )),
+    }
+}
+
+fn print_report(report: &SimulationReport) {
+    println!("termination: {:?}", report.termination);
+    println!("rounds: {}", report.rounds);
+    println!("decided: {}", report.decided.join(", "));
+    println!(
+        "messages: {} sent, {} delivered, {} duplicated, {} dropped",
+        report.messages_sent,
+        report.messages_delivered,
+        report.messages_duplicated,
+        report.messages_dropped
+    );
+    for (kind, count) in &report.message_counts {
+        println!("\t{kind}: {count}");
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn args(line: &str) -> Vec<String> {
+        line.split_whitespace().map(String::from).collect()
+    }
+
+    #[test]
+    fn parses_sweep() {
+        let (command, options) = parse(&args(
+            "sweep --nodes 3,5 --seeds 2..5 --drop 0,0.1 --format json",
+        ))
+        .unwrap();
+        assert_eq!(command, Command::Sweep);
+        assert_eq!(options.nodes, vec![3, 5]);
+        assert_eq!(options.seeds, vec![2, 3, 4]);
+        assert_eq!(options.drops, vec![0.0, 0.1]);
+        assert!(options.json);
+        assert_eq!(options.servers, Options::default().servers);
+    }
+
+    #[test]
+    fn parses_diagram_filter() {
+        let (_, options) = parse(&args(
+            "run --diagram run.svg --diagram-nodes 0,2 --diagram-rounds 10..20",
+        ))
+        .unwrap();
+        assert_eq!(options.diagram.as_deref(), Some("run.svg"));
+        let filter = options.diagram_filter;
+        assert_eq!(filter.nodes, Some([0, 2].into_iter().collect()));
+        assert_eq!(filter.rounds, Some(10..20));
+    }
+
+    #[test]
+    fn rejects_invalid_arguments() {
+        assert!(parse(&args("")).is_err());
+        assert!(parse(&args("replay")).is_err());
+        assert!(parse(&args("run --nodes")).is_err());
+        assert!(parse(&args("run --nodes three")).is_err());
+        assert!(parse(&args("run --algorithm raft")).is_err());
+        assert!(parse(&args("run --verbose")).is_err());
+        assert!(parse(&args("run --diagram run.png")).is_err());
+        assert!(parse(&args("run --diagram-rounds 5")).is_err());
+    }
+
+    #[test]
+    fn rejects_clamped_server_counts() {
+        assert!(check_servers::<paxos::System>(5, 4).is_ok());
+        assert!(check_servers::<paxos::System>(5, 6).is_err());
+        assert!(check_servers::<king::System>(5, 1).is_ok());
+        assert_eq!(
+            check_servers::<king::System>(5, 4),
+            Err(String::from(
+                "--servers 4 is more than the 1 the algorithm takes with 5 nodes"
+            ))
+        );
+        assert!(check_servers::<ben_or::System>(5, 2).is_ok());
+        assert!(check_servers::<ben_or::System>(5, 3).is_err());
+    }
+}
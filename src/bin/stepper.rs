@@ -0,0 +1,370 @@
+use distributed_algorithms_demo::network::NetworkConfig;
+use distributed_algorithms_demo::trace::TraceEvent;
+use distributed_algorithms_demo::{paxos, NodeState};
+use std::env;
+use std::io::{self, BufRead, Write};
+use std::process::ExitCode;
+
+const USAGE: &str = "\
+Usage: stepper [options]
+
+Drives Paxos round by round from a line prompt on stdin.
+
+Options:
+  --nodes N           number of nodes (default 3)
+  --servers N         number of servers (default 1)
+  --seed N            seed of the run, drawn if not given
+  --sync | --async    network timing (default async)
+  --latency N         maximum latency in rounds (default 10)";
+
+const COMMANDS: &str = "\
+Commands:
+  step [N]            run N rounds (default 1) and show their actions
+  run CONDITION       run until CONDITION holds or every node decided
+  drop ID             drop the packet ID in flight
+  delay ID N          deliver the scheduled packet ID N rounds later
+  nodes               show the state of every node
+  packets             show the packets in flight
+  help                show this list
+  quit
+
+Conditions:
+  round N             round N is next
+  decide              a node decides
+  action NAME         an action NAME is taken, e.g. send or drop
+  node ID VAR=VALUE   variable VAR of node ID is VALUE, e.g. node 0 C[0]=true";
+
+/// `run` gives up after this many rounds.
+const MAX_ROUNDS: usize = 10_000;
+
+#[derive(Debug, PartialEq)]
+struct Options {
+    nodes: usize,
+    servers: usize,
+    seed: Option<u64>,
+    asynchronous: bool,
+    latency: usize,
+}
+
+#[derive(Debug, PartialEq)]
+enum Input {
+    Step(usize),
+    Run(Breakpoint),
+    Drop(usize),
+    Delay(usize, usize),
+    Nodes,
+    Packets,
+    Help,
+    Quit,
+}
+
+#[derive(Debug, PartialEq)]
+enum Breakpoint {
+    Round(usize),
+    Decide,
+    Action(String),
+    Variable(usize, String, String),
+}
+
+struct Stepper {
+    system: paxos::System,
+}
+
+fn main() -> ExitCode {
+    let args: Vec<String> = env::args().skip(1).collect();
+    let options = match parse(&args) {
+        Ok(options) => options,
+        Err(e) => {
+            eprintln!("{e}\n\n{USAGE}");
+            return ExitCode::from(2);
+        }
+    };
+    let seed = options.seed.unwrap_or_else(rand::random);
+    let config = NetworkConfig::new(options.asynchronous, options.latency);
+    let system = paxos::System::new_rand_with(options.nodes, options.servers, Some(seed), config);
+    let mut stepper = Stepper { system };
+
+    let mut out = io::stdout().lock();
+    let result = writeln!(out, "seed: {seed}\n\n{COMMANDS}\n")
+        .and_then(|_| stepper.show_nodes(&mut out))
+        .and_then(|_| stepper.interact(io::stdin().lock(), &mut out));
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("{e}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn parse(args: &[String]) -> Result<Options, String> {
+    let mut options = Options {
+        nodes: 3,
+        servers: 1,
+        seed: None,
+        asynchronous: true,
+        latency: 10,
+    };
+    let mut args = args.iter();
+    while let Some(flag) = args.next() {
+        let mut value = || {
+            args.next()
+                .map(String::as_str)
+                .ok_or(format!("{flag} needs a value"))
+        };
+        match flag.as_str() {
+            "--nodes" => options.nodes = parse_value(value()?)?,
+            "--servers" => options.servers = parse_value(value()?)?,
+            "--seed" => options.seed = Some(parse_value(value()?)?),
+            "--sync" => options.asynchronous = false,
+            "--async" => options.asynchronous = true,
+            "--latency" => options.latency = parse_value(value()?)?,
+            other => return Err(format!("Unknown option {other}")),
+        }
+    }
+    if options.servers > options.nodes {
+        return Err(String::from("There are more servers than nodes"));
+    }
+    Ok(options)
+}
+
+fn parse_value<T: std::str::FromStr>(value: &str) -> Result<T, String> {
+    value
+        .trim()
+        .parse()
+        .map_err(|_| format!("Invalid value {value}"))
+}
+
+fn parse_input(line: &str) -> Result<Input, String> {
+    let words: Vec<&str> = line.split_whitespace().collect();
+    let input = match words.as_slice() {
+        ["step" | "s"] => Input::Step(1),
+        ["step" | "s", rounds] => Input::Step(parse_value(rounds)?),
+        ["run" | "r", condition @ ..] => Input::Run(parse_breakpoint(condition)?),
+        ["drop", id] => Input::Drop(parse_value(id)?),
+        ["delay", id, rounds] => Input::Delay(parse_value(id)?, parse_value(rounds)?),
+        ["nodes"] => Input::Nodes,
+        ["packets"] => Input::Packets,
+        ["help"] => Input::Help,
+        ["quit" | "q"] => Input::Quit,
+        _ => return Err(format!("Unknown command {}", line.trim())),
+    };
+    Ok(input)
+}
+
+fn parse_breakpoint(words: &[&str]) -> Result<Breakpoint, String> {
+    let breakpoint = match words {
+        ["round", round] => Breakpoint::Round(parse_value(round)?),
+        ["decide"] => Breakpoint::Decide,
+        ["action", name] => Breakpoint::Action(name.to_string()),
+        ["node", id, assignment] => match assignment.split_once('=') {
+            Some((var, value)) => {
+                Breakpoint::Variable(parse_value(id)?, var.to_string(), value.to_string())
+            }
+            None => return Err(format!("Expected VAR=VALUE instead of {assignment}")),
+        },
+        _ => return Err(format!("Unknown condition {}", words.join(" "))),
+    };
+    Ok(breakpoint)
+}
+
+impl Breakpoint {
+    /// Checked before every round with the actions of the last one.
+    fn holds(&self, system: &paxos::System, events: &[TraceEvent]) -> bool {
+        match self {
+            Breakpoint::Round(round) => system.round() >= *round,
+            Breakpoint::Decide => events.iter().any(|e| e.action == "decide"),
+            Breakpoint::Action(name) => events.iter().any(|e| e.action == name),
+            Breakpoint::Variable(id, var, value) => system
+                .node_states()
+                .get(*id)
+                .and_then(|state| state.variable(var))
+                .is_some_and(|v| v == value),
+        }
+    }
+}
+
+impl Stepper {
+    fn interact<R: BufRead, W: Write>(&mut self, input: R, out: &mut W) -> io::Result<()> {
+        write!(out, "> ")?;
+        out.flush()?;
+        for line in input.lines() {
+            let line = line?;
+            if !line.trim().is_empty() {
+                match parse_input(&line) {
+                    Ok(Input::Quit) => return Ok(()),
+                    Ok(input) => self.execute(input, out)?,
+                    Err(e) => writeln!(out, "{e}, see help")?,
+                }
+            }
+            write!(out, "> ")?;
+            out.flush()?;
+        }
+        writeln!(out)
+    }
+
+    fn execute<W: Write>(&mut self, input: Input, out: &mut W) -> io::Result<()> {
+        match input {
+            Input::Step(rounds) => {
+                for _ in 0..rounds {
+                    let events = self.system.step();
+                    show_events(&events, out)?;
+                }
+                self.show_nodes(out)?;
+                self.show_packets(out)
+            }
+            Input::Run(breakpoint) => {
+                let start = self.system.round();
+                let mut events = Vec::new();
+                loop {
+                    if breakpoint.holds(&self.system, &events) {
+                        show_events(&events, out)?;
+                        break;
+                    }
+                    if self.system.decided() || self.system.round() - start >= MAX_ROUNDS {
+                        writeln!(out, "stopped without reaching the breakpoint")?;
+                        break;
+                    }
+                    events = self.system.step();
+                }
+                self.show_nodes(out)?;
+                self.show_packets(out)
+            }
+            Input::Drop(id) => match self.system.drop_packet(id) {
+                true => writeln!(out, "dropped packet #{id}"),
+                false => writeln!(out, "no packet #{id} in flight"),
+            },
+            Input::Delay(id, rounds) => match self.system.delay_packet(id, rounds) {
+                true => writeln!(out, "delayed packet #{id} by {rounds} rounds"),
+                false => writeln!(out, "no packet #{id} scheduled by due round"),
+            },
+            Input::Nodes => self.show_nodes(out),
+            Input::Packets => self.show_packets(out),
+            Input::Help => writeln!(out, "{COMMANDS}"),
+            Input::Quit => Ok(()),
+        }
+    }
+
+    fn show_nodes<W: Write>(&self, out: &mut W) -> io::Result<()> {
+        writeln!(out, "Nodes before round {}:", self.system.round())?;
+        for state in self.system.node_states() {
+            writeln!(out, "\t{}", format_state(&state))?;
+        }
+        Ok(())
+    }
+
+    fn show_packets<W: Write>(&self, out: &mut W) -> io::Result<()> {
+        let round = self.system.round();
+        let packets = self.system.in_flight_packets();
+        writeln!(out, "Packets in flight: {}", packets.len())?;
+        for (due, packet) in packets {
+            let due = match due {
+                Some(due) => format!("in {} rounds", due.saturating_sub(round)),
+                None => String::from("unscheduled"),
+            };
+            writeln!(
+                out,
+                "\t#{} {} -> {} {:?} {due}",
+                packet.id, packet.sender, packet.receiver, packet.content
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Writes the actions of a round as the text trace does.
+fn show_events<W: Write>(events: &[TraceEvent], out: &mut W) -> io::Result<()> {
+    let mut actor = None;
+    for event in events {
+        if actor != Some((&event.actor, event.actor_id)) {
+            actor = Some((&event.actor, event.actor_id));
+            match event.actor_id {
+                Some(id) => writeln!(out, "Round {}, {} #{id}", event.round, event.actor)?,
+                None => writeln!(out, "Round {}, {}", event.round, event.actor)?,
+            }
+        }
+        writeln!(out, "\t{}", event.text)?;
+    }
+    Ok(())
+}
+
+fn format_state(state: &NodeState) -> String {
+    let variables: Vec<String> = state
+        .variables
+        .iter()
+        .map(|(var, value)| format!("{var}={value}"))
+        .collect();
+    let crashed = if state.crashed { " (crashed)" } else { "" };
+    format!(
+        "{} #{}{crashed}: {}",
+        state.kind,
+        state.id,
+        variables.join(" ")
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn stepper() -> Stepper {
+        let config = NetworkConfig::new(true, 10);
+        Stepper {
+            system: paxos::System::new_rand_with(3, 1, Some(3), config),
+        }
+    }
+
+    #[test]
+    fn parses_inputs() {
+        assert_eq!(parse_input("step"), Ok(Input::Step(1)));
+        assert_eq!(parse_input(" s 5 "), Ok(Input::Step(5)));
+        assert_eq!(parse_input("delay 3 4"), Ok(Input::Delay(3, 4)));
+        assert_eq!(
+            parse_input("run node 0 C[0]=true"),
+            Ok(Input::Run(Breakpoint::Variable(
+                0,
+                String::from("C[0]"),
+                String::from("true")
+            )))
+        );
+        assert!(parse_input("run node 0 C[0]").is_err());
+        assert!(parse_input("drop").is_err());
+        assert!(parse_input("run forever").is_err());
+        assert!(parse(&[String::from("--servers"), String::from("4")]).is_err());
+    }
+
+    #[test]
+    fn runs_to_breakpoints() {
+        let mut stepper = stepper();
+        let mut out = Vec::new();
+        let script = "step\nstep\nrun action receive\nrun round 10\nquit\nstep\n";
+        stepper.interact(script.as_bytes(), &mut out).unwrap();
+        let out = String::from_utf8(out).unwrap();
+        assert!(out.contains("Round 0, Client #1\n\tstore t = (1, 1)\n"));
+        assert!(out.contains("\treceived Ask(0, (1, "));
+        assert!(out.contains("Nodes before round 10:\n\tServer #0: t_max[*]="));
+        assert_eq!(stepper.system.round(), 10);
+
+        let mut out = Vec::new();
+        stepper
+            .execute(Input::Run(Breakpoint::Round(4)), &mut out)
+            .unwrap();
+        assert_eq!(stepper.system.round(), 10);
+    }
+
+    #[test]
+    fn drops_selected_packets() {
+        let mut stepper = stepper();
+        let mut out = Vec::new();
+        stepper.execute(Input::Step(2), &mut out).unwrap();
+        let id = stepper.system.in_flight_packets()[0].1.id;
+        stepper.execute(Input::Drop(id), &mut out).unwrap();
+        stepper.execute(Input::Drop(id), &mut out).unwrap();
+        let out = String::from_utf8(out).unwrap();
+        assert!(out.contains(&format!(
+            "dropped packet #{id}\nno packet #{id} in flight\n"
+        )));
+        let in_flight = stepper.system.in_flight_packets();
+        assert!(in_flight.iter().all(|(_, p)| p.id != id));
+    }
+}
@@ -0,0 +1,61 @@
+use rand::{rngs::StdRng, Rng};
+use std::collections::VecDeque;
+
+/// Random bits of a node, drawn from its rng or replayed from a recording.
+/// Every bit handed out is remembered, so a run can be recorded.
+pub struct Coin {
+    rng: StdRng,
+    flips: Vec<bool>,
+    replay: VecDeque<bool>,
+}
+
+impl Coin {
+    pub fn new(rng: StdRng) -> Self {
+        Coin {
+            rng,
+            flips: Vec::new(),
+            replay: VecDeque::new(),
+        }
+    }
+
+    /// The next replayed bit, drawn from the rng once they run out.
+    pub fn flip(&mut self) -> bool {
+        let flip = match self.replay.pop_front() {
+            Some(flip) => flip,
+            None => self.rng.gen(),
+        };
+        self.flips.push(flip);
+        flip
+    }
+
+    pub fn flips(&self) -> &[bool] {
+        &self.flips
+    }
+
+    /// Hands out `flips` before drawing from the rng again.
+    pub fn replay(&mut self, flips: Vec<bool>) {
+        self.replay = flips.into();
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::Coin;
+    use rand::{rngs::StdRng, SeedableRng};
+
+    #[test]
+    fn coin_replays_flips() {
+        let mut coin = Coin::new(StdRng::seed_from_u64(1));
+        let drawn: Vec<bool> = (0..16).map(|_| coin.flip()).collect();
+        assert_eq!(coin.flips(), drawn);
+
+        let mut replayed = Coin::new(StdRng::seed_from_u64(2));
+        replayed.replay(vec![true, false, true]);
+        assert_eq!(
+            (0..3).map(|_| replayed.flip()).collect::<Vec<_>>(),
+            [true, false, true]
+        );
+        replayed.flip();
+        assert_eq!(replayed.flips().len(), 4);
+    }
+}
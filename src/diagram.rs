@@ -0,0 +1,426 @@
+use crate::trace::{Field, TraceEvent};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::ops::Range;
+
+const COLUMN_WIDTH: usize = 180;
+const ROUND_HEIGHT: usize = 24;
+const HEADER_HEIGHT: usize = 50;
+const MARGIN: usize = 60;
+
+/// Which messages of a run end up in a diagram.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Filter {
+    /// Only messages between these nodes, all nodes for `None`.
+    pub nodes: Option<BTreeSet<usize>>,
+    /// Only messages sent in these rounds, all rounds for `None`.
+    pub rounds: Option<Range<usize>>,
+}
+
+/// A message from its `send` to its `receive` action.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Arrow {
+    pub sender: usize,
+    pub receiver: usize,
+    pub message: String,
+    pub sent: usize,
+    /// `None` for messages that never arrived.
+    pub received: Option<usize>,
+    /// Whether the network dropped the message.
+    pub lost: bool,
+}
+
+impl Filter {
+    fn keeps(&self, arrow: &Arrow) -> bool {
+        let nodes = self
+            .nodes
+            .as_ref()
+            .is_none_or(|nodes| nodes.contains(&arrow.sender) && nodes.contains(&arrow.receiver));
+        let rounds = self
+            .rounds
+            .as_ref()
+            .is_none_or(|rounds| rounds.contains(&arrow.sent));
+        nodes && rounds
+    }
+}
+
+/// Index of every `send` in `events` together with the index of the first
+/// `receive` of the same packet. Later receives, e.g. of duplicated
+/// packets, are left out.
+pub fn messages(events: &[TraceEvent]) -> Vec<(usize, Option<usize>)> {
+    let mut messages = Vec::new();
+    let mut unmatched: HashMap<usize, usize> = HashMap::new(); // packet id -> message
+    for (index, event) in events.iter().enumerate() {
+        let (Some(_), Some(Field::Int(packet))) = (event.actor_id, event.field("packet")) else {
+            continue;
+        };
+        match event.action {
+            "send" => {
+                unmatched.insert(*packet, messages.len());
+                messages.push((index, None));
+            }
+            "receive" => {
+                if let Some(message) = unmatched.remove(packet) {
+                    messages[message].1 = Some(index);
+                }
+            }
+            _ => {}
+        }
+    }
+    messages
+}
+
+/// Sends without a receiver or message are left out.
+pub fn arrows(events: &[TraceEvent], filter: &Filter) -> Vec<Arrow> {
+    let dropped: HashSet<usize> = events
+        .iter()
+        .filter(|event| event.actor_id.is_none() && event.action == "drop")
+        .filter_map(|event| match event.field("packet") {
+            Some(Field::Int(packet)) => Some(*packet),
+            _ => None,
+        })
+        .collect();
+    let mut arrows: Vec<Arrow> = messages(events)
+        .into_iter()
+        .filter_map(|(send, receive)| {
+            let event = &events[send];
+            let (Some(sender), Some(Field::Int(packet))) = (event.actor_id, event.field("packet"))
+            else {
+                return None;
+            };
+            let (Some(Field::Int(receiver)), Some(Field::Text(message))) =
+                (event.field("receiver"), event.field("message"))
+            else {
+                return None;
+            };
+            Some(Arrow {
+                sender,
+                receiver: *receiver,
+                message: message.clone(),
+                sent: event.round,
+                received: receive.map(|receive| events[receive].round),
+                lost: receive.is_none() && dropped.contains(packet),
+            })
+        })
+        .collect();
+    arrows.retain(|arrow| filter.keeps(arrow));
+    arrows
+}
+
+/// Names of the nodes that take part in `arrows`, e.g. `Server #0`.
+fn participants(events: &[TraceEvent], arrows: &[Arrow]) -> BTreeMap<usize, String> {
+    let mut names = BTreeMap::new();
+    for arrow in arrows {
+        for id in [arrow.sender, arrow.receiver] {
+            names.insert(id, format!("Node #{id}"));
+        }
+    }
+    for event in events {
+        if let Some(id) = event.actor_id {
+            if let Some(name) = names.get_mut(&id) {
+                *name = format!("{} #{id}", event.actor);
+            }
+        }
+    }
+    names
+}
+
+/// Only messages the network dropped are lost, others were still in flight
+/// or reached a node that did not take them, e.g. a crashed one.
+fn label(arrow: &Arrow) -> String {
+    match (arrow.received, arrow.lost) {
+        (Some(received), _) => format!("{} (round {} to {received})", arrow.message, arrow.sent),
+        (None, true) => format!("{} (round {}, lost)", arrow.message, arrow.sent),
+        (None, false) => format!("{} (round {}, not received)", arrow.message, arrow.sent),
+    }
+}
+
+/// Arrows ordered by the round they arrive in, the others by the round they
+/// were sent in.
+fn in_order(arrows: &[Arrow]) -> Vec<&Arrow> {
+    let mut ordered: Vec<&Arrow> = arrows.iter().collect();
+    ordered.sort_by_key(|arrow| arrow.received.unwrap_or(arrow.sent));
+    ordered
+}
+
+pub fn to_mermaid(events: &[TraceEvent], filter: &Filter) -> String {
+    // Mermaid reads `#` and `;` as the start of an entity and a new line.
+    let escape = |text: &str| {
+        text.chars()
+            .map(|c| match c {
+                '#' => String::from("#35;"),
+                ';' => String::from("#59;"),
+                c => c.to_string(),
+            })
+            .collect::<String>()
+    };
+    let arrows = arrows(events, filter);
+    let mut diagram = String::from("sequenceDiagram\n");
+    for (id, name) in participants(events, &arrows) {
+        diagram += &format!("    participant n{id} as {}\n", escape(&name));
+    }
+    for arrow in in_order(&arrows) {
+        let line = match (arrow.received, arrow.lost) {
+            (Some(_), _) => "->>",
+            (None, true) => "-x",
+            (None, false) => "--x",
+        };
+        diagram += &format!(
+            "    n{}{line}n{}: {}\n",
+            arrow.sender,
+            arrow.receiver,
+            escape(&label(arrow))
+        );
+    }
+    diagram
+}
+
+pub fn to_plantuml(events: &[TraceEvent], filter: &Filter) -> String {
+    // Quotes end a name and line breaks a line, `\n` breaks a label.
+    let escape = |text: &str| {
+        text.replace('\\', "<U+005C>")
+            .replace('"', "<U+0022>")
+            .replace('\n', "\\n")
+    };
+    let arrows = arrows(events, filter);
+    let mut diagram = String::from("@startuml\n");
+    for (id, name) in participants(events, &arrows) {
+        diagram += &format!("participant \"{}\" as n{id}\n", escape(&name));
+    }
+    for arrow in in_order(&arrows) {
+        let line = match (arrow.received, arrow.lost) {
+            (Some(_), _) => "->",
+            (None, true) => "->x",
+            (None, false) => "-->x",
+        };
+        diagram += &format!(
+            "n{} {line} n{} : {}\n",
+            arrow.sender,
+            arrow.receiver,
+            escape(&label(arrow))
+        );
+    }
+    diagram + "@enduml\n"
+}
+
+/// A space-time diagram with one vertical lifeline per node and time
+/// flowing downwards, one row per round.
+pub fn to_svg(events: &[TraceEvent], filter: &Filter) -> String {
+    let arrows = arrows(events, filter);
+    let names = participants(events, &arrows);
+    let columns: BTreeMap<usize, usize> =
+        names.keys().enumerate().map(|(c, &id)| (id, c)).collect();
+    let first = arrows.iter().map(|a| a.sent).min().unwrap_or(0);
+    let last = arrows
+        .iter()
+        .map(|a| a.received.unwrap_or(a.sent + 1))
+        .max()
+        .unwrap_or(first);
+    let x = |id: usize| MARGIN + columns[&id] * COLUMN_WIDTH + COLUMN_WIDTH / 2;
+    let y = |round: usize| HEADER_HEIGHT + (round - first) * ROUND_HEIGHT;
+    let width = 2 * MARGIN + names.len() * COLUMN_WIDTH;
+    let height = y(last) + HEADER_HEIGHT;
+
+    let mut svg = format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{width}\" height=\"{height}\" \
+         font-family=\"monospace\" font-size=\"11\">\n"
+    );
+    svg += "<defs><marker id=\"arrow\" markerWidth=\"8\" markerHeight=\"8\" refX=\"8\" \
+            refY=\"4\" orient=\"auto\"><path d=\"M0,0 L8,4 L0,8 z\"/></marker></defs>\n";
+    for round in first..=last {
+        svg += &format!(
+            "<text x=\"{}\" y=\"{}\" text-anchor=\"end\" fill=\"gray\">{round}</text>\n",
+            MARGIN - 10,
+            y(round) + 4
+        );
+    }
+    for (&id, name) in &names {
+        svg += &format!(
+            "<text x=\"{}\" y=\"{}\" text-anchor=\"middle\" font-weight=\"bold\">{}</text>\n",
+            x(id),
+            HEADER_HEIGHT - 20,
+            xml_escape(name)
+        );
+        svg += &format!(
+            "<line x1=\"{0}\" y1=\"{1}\" x2=\"{0}\" y2=\"{2}\" stroke=\"black\"/>\n",
+            x(id),
+            y(first),
+            y(last)
+        );
+    }
+    for arrow in &arrows {
+        let (x1, y1) = (x(arrow.sender), y(arrow.sent));
+        let (x2, y2, style) = match arrow.received {
+            Some(received) => (x(arrow.receiver), y(received), "stroke=\"steelblue\""),
+            // Messages that never arrived end halfway.
+            None => (
+                (x1 + x(arrow.receiver)) / 2,
+                y1 + ROUND_HEIGHT / 2,
+                match arrow.lost {
+                    true => "stroke=\"firebrick\" stroke-dasharray=\"4\"",
+                    false => "stroke=\"gray\" stroke-dasharray=\"4\"",
+                },
+            ),
+        };
+        svg += &format!(
+            "<line x1=\"{x1}\" y1=\"{y1}\" x2=\"{x2}\" y2=\"{y2}\" {style} \
+             marker-end=\"url(#arrow)\"><title>{}</title></line>\n",
+            xml_escape(&label(arrow))
+        );
+        svg += &format!(
+            "<text x=\"{}\" y=\"{}\" text-anchor=\"middle\">{}</text>\n",
+            (x1 + x2) / 2,
+            (y1 + y2) / 2 - 3,
+            xml_escape(&arrow.message)
+        );
+    }
+    svg + "</svg>\n"
+}
+
+fn xml_escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::trace::Traceable;
+
+    #[derive(Debug)]
+    enum Action {
+        Send(usize, usize, &'static str),
+        Receive(usize, usize, &'static str),
+        Drop(usize),
+        /// A send without receiver and message.
+        Malformed(usize),
+    }
+
+    impl Traceable for Action {
+        fn action(&self) -> &'static str {
+            match self {
+                Action::Send(..) => "send",
+                Action::Receive(..) => "receive",
+                Action::Drop(_) => "drop",
+                Action::Malformed(_) => "send",
+            }
+        }
+
+        fn fields(&self) -> Vec<(&'static str, Field)> {
+            match self {
+                Action::Send(receiver, packet, message) => vec![
+                    ("receiver", (*receiver).into()),
+                    ("packet", (*packet).into()),
+                    ("message", (*message).into()),
+                ],
+                Action::Receive(sender, packet, message) => vec![
+                    ("sender", (*sender).into()),
+                    ("packet", (*packet).into()),
+                    ("message", (*message).into()),
+                ],
+                Action::Drop(packet) | Action::Malformed(packet) => {
+                    vec![("packet", (*packet).into())]
+                }
+            }
+        }
+    }
+
+    fn events() -> Vec<TraceEvent> {
+        let server = Some(("Server", Some(0)));
+        let client = |id| Some(("Client", Some(id)));
+        // The resent Ask overtakes the first one and is duplicated, the
+        // network drops the first Ask of client 2 and the second one is still
+        // in flight at the end.
+        vec![
+            TraceEvent::new(0, client(1), &Action::Send(0, 0, "Ask(0, (1, 1))")),
+            TraceEvent::new(0, client(2), &Action::Send(0, 1, "Ask(0, (1, 2))")),
+            TraceEvent::new(1, Some(("Network", None)), &Action::Drop(1)),
+            TraceEvent::new(1, client(1), &Action::Send(0, 2, "Ask(0, (1, 1))")),
+            TraceEvent::new(2, server, &Action::Receive(1, 2, "Ask(0, (1, 1))")),
+            TraceEvent::new(2, server, &Action::Send(1, 3, "Ok(0, (0, 0), ⊥)")),
+            TraceEvent::new(3, server, &Action::Receive(1, 0, "Ask(0, (1, 1))")),
+            TraceEvent::new(4, server, &Action::Receive(1, 2, "Ask(0, (1, 1))")),
+            TraceEvent::new(3, client(2), &Action::Send(0, 4, "Ask(0, (2, 2))")),
+            TraceEvent::new(4, client(2), &Action::Malformed(5)),
+            TraceEvent::new(5, client(1), &Action::Receive(0, 3, "Ok(0, (0, 0), ⊥)")),
+        ]
+    }
+
+    #[test]
+    fn matches_sends_and_receives() {
+        let arrows = arrows(&events(), &Filter::default());
+        let rounds: Vec<(usize, usize, usize, Option<usize>)> = arrows
+            .iter()
+            .map(|a| (a.sender, a.receiver, a.sent, a.received))
+            .collect();
+        assert_eq!(
+            rounds,
+            vec![
+                (1, 0, 0, Some(3)),
+                (2, 0, 0, None),
+                (1, 0, 1, Some(2)),
+                (0, 1, 2, Some(5)),
+                (2, 0, 3, None)
+            ]
+        );
+        let lost: Vec<bool> = arrows.iter().map(|a| a.lost).collect();
+        assert_eq!(lost, vec![false, true, false, false, false]);
+
+        let filter = Filter {
+            nodes: Some(BTreeSet::from([0, 2])),
+            rounds: None,
+        };
+        assert_eq!(super::arrows(&events(), &filter).len(), 2);
+        let filter = Filter {
+            nodes: None,
+            rounds: Some(2..3),
+        };
+        assert_eq!(
+            super::arrows(&events(), &filter)[0].message,
+            "Ok(0, (0, 0), ⊥)"
+        );
+    }
+
+    #[test]
+    fn writes_sequence_diagrams() {
+        let mermaid = to_mermaid(&events(), &Filter::default());
+        assert!(mermaid.starts_with("sequenceDiagram\n    participant n0 as Server #35;0\n"));
+        assert!(mermaid.contains("    n1->>n0: Ask(0, (1, 1)) (round 0 to 3)\n"));
+        assert!(mermaid.contains("    n2-xn0: Ask(0, (1, 2)) (round 0, lost)\n"));
+        assert!(mermaid.contains("    n2--xn0: Ask(0, (2, 2)) (round 3, not received)\n"));
+
+        let plantuml = to_plantuml(&events(), &Filter::default());
+        assert!(plantuml.contains("participant \"Client #1\" as n1\n"));
+        assert!(plantuml.contains("n0 -> n1 : Ok(0, (0, 0), ⊥) (round 2 to 5)\n"));
+        assert!(plantuml.contains("n2 ->x n0 : Ask(0, (1, 2)) (round 0, lost)\n"));
+        assert!(plantuml.contains("n2 -->x n0 : Ask(0, (2, 2)) (round 3, not received)\n"));
+        assert!(plantuml.ends_with("@enduml\n"));
+
+        let svg = to_svg(&events(), &Filter::default());
+        assert!(svg.starts_with("<svg "));
+        assert_eq!(svg.matches("marker-end").count(), 5);
+        assert_eq!(svg.matches("stroke=\"firebrick\"").count(), 1);
+        assert!(svg.contains(">Client #2</text>"));
+        assert!(svg.ends_with("</svg>\n"));
+    }
+
+    #[test]
+    fn plantuml_escapes_names_and_labels() {
+        let events = vec![
+            TraceEvent::new(
+                0,
+                Some(("Say \"hi\"", Some(0))),
+                &Action::Send(1, 0, "a\\nb\nc"),
+            ),
+            TraceEvent::new(
+                1,
+                Some(("Node", Some(1))),
+                &Action::Receive(0, 0, "a\\nb\nc"),
+            ),
+        ];
+        let plantuml = to_plantuml(&events, &Filter::default());
+        assert!(plantuml.contains("participant \"Say <U+0022>hi<U+0022> #0\" as n0\n"));
+        assert!(plantuml.contains("n0 -> n1 : a<U+005C>nb\\nc (round 0 to 1)\n"));
+    }
+}
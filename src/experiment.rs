@@ -0,0 +1,387 @@
+use crate::network::NetworkConfig;
+use crate::trace::json_string;
+use crate::{Logger, SimulationReport, System, Termination};
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::thread;
+
+/// Grid of parameters to simulate. Every combination of node count, server
+/// count, latency and drop probability is run once per seed.
+#[derive(Clone, Debug)]
+pub struct Sweep {
+    pub node_counts: Vec<usize>,
+    pub server_counts: Vec<usize>,
+    pub max_latencies: Vec<usize>,
+    pub drop_probabilities: Vec<f64>,
+    pub seeds: Vec<u64>,
+    pub asynchronous: bool,
+    pub max_rounds: Option<usize>,
+    pub threads: usize,
+}
+
+/// One point of the grid.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Configuration {
+    pub node_count: usize,
+    pub server_count: usize,
+    pub max_latency: usize,
+    pub drop_probability: f64,
+}
+
+/// Mean, median and 99th percentile of a sample.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Summary {
+    pub mean: f64,
+    pub median: f64,
+    pub p99: f64,
+}
+
+/// Aggregated reports of all seeds of one configuration.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Statistics {
+    pub configuration: Configuration,
+    pub runs: usize,
+    pub decided: usize,
+    pub max_rounds: usize,
+    pub safety_violations: usize,
+    /// Rounds until every node decided, over the runs that decided.
+    pub rounds: Option<Summary>,
+    pub messages_sent: Option<Summary>,
+    pub messages_dropped: Option<Summary>,
+    /// Mean number of messages sent per kind.
+    pub message_counts: BTreeMap<String, f64>,
+}
+
+impl Sweep {
+    /// Runs on an asynchronous network with a latency of up to 10 rounds,
+    /// no packet loss, at most 10000 rounds and one thread per core.
+    pub fn new(node_counts: Vec<usize>, server_counts: Vec<usize>, seeds: Vec<u64>) -> Self {
+        let threads = thread::available_parallelism().map_or(1, |n| n.get());
+        Sweep {
+            node_counts,
+            server_counts,
+            max_latencies: vec![10],
+            drop_probabilities: vec![0.0],
+            seeds,
+            asynchronous: true,
+            max_rounds: Some(10_000),
+            threads,
+        }
+    }
+
+    /// Combinations where there are more servers than nodes are skipped.
+    pub fn configurations(&self) -> Vec<Configuration> {
+        let mut configurations = Vec::new();
+        for &node_count in &self.node_counts {
+            for &server_count in &self.server_counts {
+                if server_count > node_count {
+                    continue;
+                }
+                for &max_latency in &self.max_latencies {
+                    for &drop_probability in &self.drop_probabilities {
+                        configurations.push(Configuration {
+                            node_count,
+                            server_count,
+                            max_latency,
+                            drop_probability,
+                        });
+                    }
+                }
+            }
+        }
+        configurations
+    }
+
+    /// Simulates every configuration with every seed on `threads` workers
+    /// and aggregates the reports per configuration. The result does not
+    /// depend on the number of threads.
+    pub fn run<S: System>(&self) -> Vec<Statistics> {
+        let configurations = self.configurations();
+        let jobs: Vec<(usize, u64)> = (0..configurations.len())
+            .flat_map(|c| self.seeds.iter().map(move |&seed| (c, seed)))
+            .collect();
+
+        let next = AtomicUsize::new(0);
+        let reports = Mutex::new(vec![None; jobs.len()]);
+        thread::scope(|scope| {
+            for _ in 0..self.threads.max(1) {
+                scope.spawn(|| loop {
+                    let job = next.fetch_add(1, Ordering::Relaxed);
+                    let Some(&(c, seed)) = jobs.get(job) else {
+                        break;
+                    };
+                    let report = self.simulate::<S>(&configurations[c], seed);
+                    reports.lock().unwrap()[job] = Some(report);
+                });
+            }
+        });
+
+        let reports: Vec<SimulationReport> = reports
+            .into_inner()
+            .unwrap()
+            .into_iter()
+            .map(|r| r.expect("Job was not run"))
+            .collect();
+        configurations
+            .into_iter()
+            .enumerate()
+            .map(|(c, configuration)| {
+                let runs = &reports[c * self.seeds.len()..(c + 1) * self.seeds.len()];
+                Statistics::new(configuration, runs)
+            })
+            .collect()
+    }
+
+    fn simulate<S: System>(&self, configuration: &Configuration, seed: u64) -> SimulationReport {
+        let mut config = NetworkConfig::new(self.asynchronous, configuration.max_latency);
+        config.drop_probability = configuration.drop_probability;
+        let mut system = S::new_with_network(
+            configuration.node_count,
+            configuration.server_count,
+            Some(seed),
+            config,
+        );
+        system.simulate_with(self.max_rounds, &mut Logger::new(None))
+    }
+}
+
+impl Summary {
+    /// `None` for an empty sample. Percentiles use the nearest rank.
+    pub fn of(sample: &[usize]) -> Option<Self> {
+        if sample.is_empty() {
+            return None;
+        }
+        let mut sorted = sample.to_vec();
+        sorted.sort();
+        let len = sorted.len();
+        let mean = sorted.iter().sum::<usize>() as f64 / len as f64;
+        let median = match len % 2 {
+            0 => (sorted[len / 2 - 1] + sorted[len / 2]) as f64 / 2.0,
+            _ => sorted[len / 2] as f64,
+        };
+        let rank = (len as f64 * 0.99).ceil() as usize;
+        let p99 = sorted[rank.max(1) - 1] as f64;
+        Some(Summary { mean, median, p99 })
+    }
+}
+
+impl Statistics {
+    pub fn new(configuration: Configuration, reports: &[SimulationReport]) -> Self {
+        let count = |termination| {
+            reports
+                .iter()
+                .filter(|r| r.termination == termination)
+                .count()
+        };
+        let rounds: Vec<usize> = reports
+            .iter()
+            .filter(|r| r.termination == Termination::Decided)
+            .map(|r| r.rounds)
+            .collect();
+        let sent: Vec<usize> = reports.iter().map(|r| r.messages_sent).collect();
+        let dropped: Vec<usize> = reports.iter().map(|r| r.messages_dropped).collect();
+
+        let mut message_counts = BTreeMap::new();
+        for report in reports {
+            for (kind, count) in &report.message_counts {
+                *message_counts.entry(kind.clone()).or_insert(0.0) += *count as f64;
+            }
+        }
+        for count in message_counts.values_mut() {
+            *count /= reports.len() as f64;
+        }
+
+        Statistics {
+            configuration,
+            runs: reports.len(),
+            decided: count(Termination::Decided),
+            max_rounds: count(Termination::MaxRounds),
+            safety_violations: count(Termination::SafetyViolation),
+            rounds: Summary::of(&rounds),
+            messages_sent: Summary::of(&sent),
+            messages_dropped: Summary::of(&dropped),
+            message_counts,
+        }
+    }
+
+    /// Share of runs that did not decide, whether they ran out of rounds or
+    /// violated safety.
+    pub fn failure_rate(&self) -> f64 {
+        match self.runs {
+            0 => 0.0,
+            runs => (runs - self.decided) as f64 / runs as f64,
+        }
+    }
+}
+
+/// One row per configuration with a column for the mean count of every
+/// message kind seen in any of them. Missing summaries are left empty.
+pub fn to_csv(statistics: &[Statistics]) -> String {
+    let kinds = message_kinds(statistics);
+    let mut header = vec![
+        "node_count",
+        "server_count",
+        "max_latency",
+        "drop_probability",
+        "runs",
+        "decided",
+        "max_rounds",
+        "safety_violations",
+        "failure_rate",
+    ]
+    .into_iter()
+    .map(String::from)
+    .collect::<Vec<String>>();
+    for summary in ["rounds", "messages_sent", "messages_dropped"] {
+        for field in ["mean", "median", "p99"] {
+            header.push(format!("{summary}_{field}"));
+        }
+    }
+    header.extend(kinds.iter().map(|kind| format!("{kind}_mean")));
+
+    let mut csv = header.join(",") + "\n";
+    for stats in statistics {
+        let c = &stats.configuration;
+        let mut row = vec![
+            c.node_count.to_string(),
+            c.server_count.to_string(),
+            c.max_latency.to_string(),
+            c.drop_probability.to_string(),
+            stats.runs.to_string(),
+            stats.decided.to_string(),
+            stats.max_rounds.to_string(),
+            stats.safety_violations.to_string(),
+            stats.failure_rate().to_string(),
+        ];
+        for summary in [stats.rounds, stats.messages_sent, stats.messages_dropped] {
+            match summary {
+                Some(s) => row.extend([s.mean, s.median, s.p99].map(|v| v.to_string())),
+                None => row.extend([String::new(), String::new(), String::new()]),
+            }
+        }
+        for kind in &kinds {
+            let mean = stats.message_counts.get(kind).copied().unwrap_or(0.0);
+            row.push(mean.to_string());
+        }
+        csv += &(row.join(",") + "\n");
+    }
+    csv
+}
+
+/// An array with one object per configuration, missing summaries are `null`.
+pub fn to_json(statistics: &[Statistics]) -> String {
+    let summary = |summary: Option<Summary>| match summary {
+        Some(s) => format!(
+            "{{\"mean\":{},\"median\":{},\"p99\":{}}}",
+            s.mean, s.median, s.p99
+        ),
+        None => String::from("null"),
+    };
+    let objects: Vec<String> = statistics
+        .iter()
+        .map(|stats| {
+            let c = &stats.configuration;
+            let counts: Vec<String> = stats
+                .message_counts
+                .iter()
+                .map(|(kind, mean)| format!("{}:{mean}", json_string(kind)))
+                .collect();
+            format!(
+                "{{\"node_count\":{},\"server_count\":{},\"max_latency\":{},\
+                 \"drop_probability\":{},\"runs\":{},\"decided\":{},\"max_rounds\":{},\
+                 \"safety_violations\":{},\"failure_rate\":{},\"rounds\":{},\
+                 \"messages_sent\":{},\"messages_dropped\":{},\"message_counts\":{{{}}}}}",
+                c.node_count,
+                c.server_count,
+                c.max_latency,
+                c.drop_probability,
+                stats.runs,
+                stats.decided,
+                stats.max_rounds,
+                stats.safety_violations,
+                stats.failure_rate(),
+                summary(stats.rounds),
+                summary(stats.messages_sent),
+                summary(stats.messages_dropped),
+                counts.join(","),
+            )
+        })
+        .collect();
+    format!("[{}]", objects.join(","))
+}
+
+fn message_kinds(statistics: &[Statistics]) -> Vec<String> {
+    let mut kinds: Vec<String> = statistics
+        .iter()
+        .flat_map(|stats| stats.message_counts.keys().cloned())
+        .collect();
+    kinds.sort();
+    kinds.dedup();
+    kinds
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{king, paxos};
+
+    #[test]
+    fn summary_uses_nearest_rank() {
+        let sample: Vec<usize> = (1..=200).collect();
+        let summary = Summary::of(&sample).unwrap();
+        assert_eq!(summary.mean, 100.5);
+        assert_eq!(summary.median, 100.5);
+        assert_eq!(summary.p99, 198.0);
+        assert_eq!(Summary::of(&[7]).unwrap().p99, 7.0);
+        assert_eq!(Summary::of(&[]), None);
+    }
+
+    #[test]
+    fn sweep_is_independent_of_threads() {
+        let mut sweep = Sweep::new(vec![3, 5], vec![1, 2, 4], (0..6).collect());
+        sweep.drop_probabilities = vec![0.0, 0.1];
+        sweep.max_rounds = Some(2000);
+        sweep.threads = 1;
+        let sequential = sweep.run::<paxos::System>();
+        sweep.threads = 4;
+        let parallel = sweep.run::<paxos::System>();
+        assert_eq!(sequential, parallel);
+
+        // 4 servers do not fit into 3 nodes.
+        assert_eq!(sequential.len(), 10);
+        for stats in &sequential {
+            assert_eq!(stats.runs, 6);
+            assert_eq!(stats.safety_violations, 0);
+            assert_eq!(
+                stats.decided + stats.max_rounds + stats.safety_violations,
+                6
+            );
+        }
+        let lossless = &sequential[0];
+        assert_eq!(lossless.configuration.drop_probability, 0.0);
+        assert_eq!(lossless.messages_dropped.unwrap().p99, 0.0);
+    }
+
+    #[test]
+    fn sweep_writes_csv_and_json() {
+        let mut sweep = Sweep::new(vec![5, 9], vec![1], (0..4).collect());
+        sweep.max_rounds = Some(100);
+        let statistics = sweep.run::<king::System>();
+        assert!(statistics.iter().all(|s| s.failure_rate() == 0.0));
+
+        let csv = to_csv(&statistics);
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(lines.len(), 3);
+        assert!(lines[0].starts_with("node_count,server_count,"));
+        assert!(lines[0].ends_with("King_mean,Value_mean"));
+        let columns = lines[0].split(',').count();
+        assert!(lines[1..].iter().all(|l| l.split(',').count() == columns));
+        assert!(lines[1].starts_with("5,1,10,0,4,4,0,0,0,"));
+
+        let json = to_json(&statistics);
+        assert!(json.starts_with("[{\"node_count\":5,"));
+        assert!(json.contains("\"message_counts\":{\"King\":"));
+        assert_eq!(json.matches("\"runs\":4").count(), 2);
+    }
+}
@@ -0,0 +1,449 @@
+pub mod byzantine;
+pub mod honest;
+
+use super::{Logger, NodeState, SimulationReport, Termination};
+use crate::behavior::Behavior;
+use crate::coin::Coin;
+use crate::network::{Kind, Link, Network, NetworkConfig, Packet, Recording, Timing};
+use crate::trace::{Actor, Field, Traceable};
+use byzantine::Byzantine;
+use honest::Honest;
+use rand::SeedableRng;
+use rand::{self, rngs::StdRng, Rng};
+use std::{cell::RefCell, fmt::Debug, rc::Rc};
+
+type Phase = usize;
+type LinkInterface = Rc<RefCell<Link<Message>>>;
+
+#[derive(Debug, Clone, Copy)]
+pub enum Message {
+    Value(Phase, bool),
+    King(Phase, bool),
+}
+
+impl Kind for Message {
+    fn kind(&self) -> &'static str {
+        match self {
+            Self::Value(..) => "Value",
+            Self::King(..) => "King",
+        }
+    }
+}
+
+/// Number of byzantine nodes the algorithm tolerates.
+fn max_faults(node_count: usize) -> usize {
+    (node_count.max(1) - 1) / 4
+}
+
+trait Node: Actor {
+    fn exec(&mut self, logger: &mut Logger);
+    fn is_honest(&self) -> bool;
+    fn get_input(&self) -> bool;
+    fn get_decision(&self) -> Option<bool>;
+    /// Current values of the node's variables.
+    fn state(&self) -> Vec<(String, String)>;
+    /// The random bits of nodes that draw any.
+    fn coin(&self) -> Option<&Coin> {
+        None
+    }
+    fn coin_mut(&mut self) -> Option<&mut Coin> {
+        None
+    }
+}
+
+enum Action {
+    Store(String, String),
+    Send(usize, usize, Message), // receiver, packet id, message
+    Receive(Packet<Message>),
+    Check(String, String, bool),
+    Decide(bool),
+}
+
+impl Debug for Action {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Store(var, value) => write!(f, "store {var} = {value}"),
+            Self::Send(receiver_id, _, message) => write!(f, "send {message:?} to {receiver_id}"),
+            Self::Receive(packet) => {
+                write!(f, "received {:?} from {}", packet.content, packet.sender)
+            }
+            Self::Check(condition, values, result) => {
+                write!(f, "check {condition}: {values} => {result}")
+            }
+            Self::Decide(value) => write!(f, "decides for {}", *value as u8),
+        }
+    }
+}
+
+impl Traceable for Action {
+    fn action(&self) -> &'static str {
+        match self {
+            Self::Store(..) => "store",
+            Self::Send(..) => "send",
+            Self::Receive(..) => "receive",
+            Self::Check(..) => "check",
+            Self::Decide(..) => "decide",
+        }
+    }
+
+    fn fields(&self) -> Vec<(&'static str, Field)> {
+        match self {
+            Self::Store(var, value) => vec![
+                ("variable", var.as_str().into()),
+                ("value", value.as_str().into()),
+            ],
+            Self::Send(receiver, id, message) => vec![
+                ("receiver", (*receiver).into()),
+                ("packet", (*id).into()),
+                ("message", Field::debug(message)),
+            ],
+            Self::Receive(packet) => vec![
+                ("sender", packet.sender.into()),
+                ("packet", packet.id.into()),
+                ("clock", Field::Clock(packet.clock.clone())),
+                ("message", Field::debug(&packet.content)),
+            ],
+            Self::Check(condition, values, result) => vec![
+                ("condition", condition.as_str().into()),
+                ("values", values.as_str().into()),
+                ("result", (*result).into()),
+            ],
+            Self::Decide(value) => vec![("value", (*value).into())],
+        }
+    }
+}
+
+/// The king of every phase is the node with the phase number as id.
+fn king(phase: Phase) -> usize {
+    phase
+}
+
+/// Phase King byzantine agreement on a synchronous network. Every node
+/// assumes up to `(n - 1) / 4` byzantine nodes and runs one phase of two
+/// rounds more than that.
+pub struct System {
+    nodes: Vec<Box<dyn Node>>,
+    network: Network<Message>,
+    decision_rounds: Vec<Option<usize>>,
+}
+
+impl crate::System for System {
+    /// The `server_count` byzantine nodes take the lowest ids, so they are
+    /// the first kings.
+    fn new_rand(node_count: usize, server_count: usize, seed: Option<u64>) -> Self {
+        Self::new_with_network(node_count, server_count, seed, NetworkConfig::new(false, 0))
+    }
+
+    fn new_with_network(
+        node_count: usize,
+        server_count: usize,
+        seed: Option<u64>,
+        config: NetworkConfig,
+    ) -> Self {
+        let byzantine = (0..server_count.min(max_faults(node_count)))
+            .map(|id| (id, Behavior::Equivocate))
+            .collect();
+        let mut rng = match seed {
+            Some(seed) => StdRng::seed_from_u64(seed),
+            None => StdRng::from_entropy(),
+        };
+        let inputs = (0..node_count).map(|_| rng.gen::<bool>()).collect();
+        Self::with_config(inputs, byzantine, &mut rng, config)
+    }
+
+    fn from_recording(
+        node_count: usize,
+        server_count: usize,
+        config: NetworkConfig,
+        recording: Recording,
+    ) -> Result<Self, String> {
+        let byzantine = (0..server_count.min(max_faults(node_count)))
+            .map(|id| (id, Behavior::Equivocate))
+            .collect();
+        let inputs = (0..node_count)
+            .map(|id| recording.input(id))
+            .collect::<Result<_, _>>()?;
+        let mut system =
+            Self::with_config(inputs, byzantine, &mut StdRng::seed_from_u64(0), config);
+        system.replay(recording);
+        Ok(system)
+    }
+
+    /// The `(n - 1) / 4` byzantine nodes Phase King tolerates.
+    fn max_servers(node_count: usize) -> usize {
+        max_faults(node_count)
+    }
+
+    fn simulate_with(
+        &mut self,
+        max_rounds: Option<usize>,
+        logger: &mut Logger,
+    ) -> SimulationReport {
+        let max_rounds = match max_rounds {
+            Some(nr) => nr,
+            None => usize::MAX,
+        };
+
+        while !self.decided() && self.network.round() < max_rounds {
+            self.step_with(logger);
+        }
+
+        let termination = match self.decided() {
+            true => Termination::Decided,
+            false => Termination::MaxRounds,
+        };
+        let rounds = self.network.round();
+        let mut report = SimulationReport::new(&self.network, rounds, termination);
+        let mut decided: Vec<_> = self.honest_decisions().into_iter().flatten().collect();
+        decided.sort();
+        decided.dedup();
+        report.decided = decided.iter().map(|d| format!("{d:?}")).collect();
+        report.decision_rounds = self.decision_rounds.clone();
+        report
+    }
+
+    fn step_with(&mut self, logger: &mut Logger) {
+        let round = self.network.round();
+        logger.log_round(round);
+        self.network.exchange_messages();
+        if !self.network.events().is_empty() {
+            logger.log_actor(&self.network);
+            for event in self.network.events() {
+                logger.log_action(event);
+            }
+        }
+        for node in self.nodes.iter_mut() {
+            node.exec(logger);
+        }
+        for (id, node) in self.nodes.iter().enumerate() {
+            if self.decision_rounds[id].is_none() && node.get_decision().is_some() {
+                self.decision_rounds[id] = Some(round);
+            }
+        }
+    }
+
+    fn node_states(&self) -> Vec<NodeState> {
+        self.nodes
+            .iter()
+            .map(|node| NodeState::new(node, false, node.state()))
+            .collect()
+    }
+
+    fn decided(&self) -> bool {
+        self.nodes
+            .iter()
+            .all(|node| !node.is_honest() || node.get_decision().is_some())
+    }
+
+    fn record(&mut self) {
+        self.network.record()
+    }
+
+    fn recording(&self) -> Option<Recording> {
+        let mut recording = self.network.recording()?.clone();
+        for (id, node) in self.nodes.iter().enumerate() {
+            recording.inputs.push((id, node.get_input().to_string()));
+        }
+        for (id, node) in self.nodes.iter().enumerate() {
+            if let Some(coin) = node.coin() {
+                recording.coins.insert(id, coin.flips().to_vec());
+            }
+        }
+        Some(recording)
+    }
+
+    fn replay(&mut self, recording: Recording) {
+        for (id, node) in self.nodes.iter_mut().enumerate() {
+            if let (Some(coin), Some(flips)) = (node.coin_mut(), recording.coins.get(&id)) {
+                coin.replay(flips.clone());
+            }
+        }
+        self.network.replay(recording)
+    }
+}
+
+impl System {
+    /// Draws a random input for every node.
+    pub fn new_rand_with(
+        node_count: usize,
+        byzantine: Vec<(usize, Behavior)>,
+        seed: Option<u64>,
+    ) -> Self {
+        let mut rng = match seed {
+            Some(seed) => StdRng::seed_from_u64(seed),
+            None => StdRng::from_entropy(),
+        };
+        let inputs = (0..node_count).map(|_| rng.gen::<bool>()).collect();
+        Self::new(inputs, byzantine, &mut rng)
+    }
+
+    /// Runs the nodes with the given inputs, inputs of byzantine nodes are
+    /// ignored.
+    pub fn new(inputs: Vec<bool>, byzantine: Vec<(usize, Behavior)>, rng: &mut StdRng) -> Self {
+        Self::with_config(inputs, byzantine, rng, NetworkConfig::new(false, 0))
+    }
+
+    /// The algorithm relies on every round being synchronous, so only the
+    /// faults of `config` are used and its timing is ignored.
+    pub fn with_config(
+        inputs: Vec<bool>,
+        byzantine: Vec<(usize, Behavior)>,
+        rng: &mut StdRng,
+        mut config: NetworkConfig,
+    ) -> Self {
+        config.timing = Timing::Synchronous;
+        let node_count = inputs.len();
+        let max_faults = max_faults(node_count);
+        let network = Network::with_config(node_count, Some(rng.gen()), config);
+        let mut nodes: Vec<Box<dyn Node>> = Vec::new();
+
+        for (id, input) in inputs.into_iter().enumerate() {
+            let link = network.get_link_ref(id);
+            match byzantine.iter().find(|(b, _)| *b == id) {
+                Some((_, behavior)) => nodes.push(Box::new(Byzantine::new(
+                    id,
+                    link,
+                    node_count,
+                    max_faults,
+                    *behavior,
+                    StdRng::seed_from_u64(rng.gen()),
+                ))),
+                None => nodes.push(Box::new(Honest::new(
+                    id, link, node_count, max_faults, input,
+                ))),
+            }
+        }
+
+        System {
+            decision_rounds: vec![None; nodes.len()],
+            nodes,
+            network,
+        }
+    }
+
+    pub fn honest_inputs(&self) -> Vec<bool> {
+        self.nodes
+            .iter()
+            .filter(|node| node.is_honest())
+            .map(|node| node.get_input())
+            .collect()
+    }
+
+    pub fn honest_decisions(&self) -> Vec<Option<bool>> {
+        self.nodes
+            .iter()
+            .filter(|node| node.is_honest())
+            .map(|node| node.get_decision())
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::behavior::Behavior;
+    use crate::System;
+    use crate::*;
+    use rand::{rngs::StdRng, SeedableRng};
+
+    fn assert_agreement(system: &king::System) {
+        let decisions = system.honest_decisions();
+        assert!(decisions.iter().all(|d| d.is_some()));
+        assert!(decisions.iter().all(|d| *d == decisions[0]));
+    }
+
+    #[test]
+    fn king_rng_is_deterministic() {
+        let system1: king::System = System::new_rand(9, 2, Some(42));
+        let system2: king::System = System::new_rand(9, 2, Some(42));
+        assert_eq!(system1.honest_inputs(), system2.honest_inputs());
+    }
+
+    #[test]
+    fn king_limits_byzantine_nodes() {
+        let system: king::System = System::new_rand(5, 4, Some(42));
+        assert_eq!(system.honest_inputs().len(), 4);
+    }
+
+    #[test]
+    fn king_agrees() {
+        let behaviors = [
+            Behavior::Silent,
+            Behavior::Constant(true),
+            Behavior::Random,
+            Behavior::Equivocate,
+        ];
+        for seed in 0..20 {
+            for behavior in behaviors {
+                let byzantine = vec![(0, behavior), (1, behavior)];
+                let mut system = king::System::new_rand_with(9, byzantine, Some(seed));
+                system.simulate(Some(100), Some("king_agrees")).unwrap();
+                assert!(system.decided());
+                assert_agreement(&system);
+            }
+        }
+    }
+
+    #[test]
+    fn king_steps_round_by_round() {
+        let byzantine = vec![(0, Behavior::Equivocate)];
+        let mut system = king::System::new_rand_with(4, byzantine, Some(1));
+        let events = system.step();
+        assert!(events.iter().any(|e| e.action == "send"));
+        let states = system.node_states();
+        assert_eq!(states[0].variable("behavior"), Some("Equivocate"));
+        assert_eq!(states[1].variable("decision"), Some("⊥"));
+        while !system.decided() {
+            system.step();
+        }
+        let states = system.node_states();
+        assert!(states[1..]
+            .iter()
+            .all(|s| s.variable("decision") != Some("⊥")));
+    }
+
+    #[test]
+    fn king_is_valid() {
+        let mut rng = StdRng::seed_from_u64(7);
+        for input in [false, true] {
+            let byzantine = vec![(0, Behavior::Constant(!input)), (5, Behavior::Equivocate)];
+            let mut system = king::System::new(vec![input; 13], byzantine, &mut rng);
+            let report = system.simulate(Some(100), None).unwrap();
+            assert_eq!(report.termination, Termination::Decided);
+            assert_eq!(report.decided, vec![format!("{input:?}")]);
+            assert_eq!(report.decision_rounds[0], None);
+            assert!(report.decision_rounds[1].is_some());
+            assert_agreement(&system);
+            assert_eq!(system.honest_decisions()[0], Some(input));
+        }
+    }
+
+    #[test]
+    fn king_replays_random_behavior() {
+        use crate::sink::MemorySink;
+        use crate::trace::{first_difference, TraceFormat};
+
+        let trace = |system: &mut king::System| {
+            let sink = MemorySink::new();
+            let mut logger = Logger::with_sink(Box::new(sink.clone()), TraceFormat::Text);
+            system.simulate_with(None, &mut logger);
+            sink.contents()
+        };
+        let byzantine = vec![(0, Behavior::Random), (1, Behavior::Random)];
+        let mut system = king::System::new_rand_with(9, byzantine.clone(), Some(3));
+        system.record();
+        let recorded = trace(&mut system);
+        let recording = system.recording().unwrap();
+        assert_eq!(recording.coins[&0].len(), 9 * 3 + 9);
+
+        let inputs = (0..9)
+            .map(|id| recording.input(id).unwrap())
+            .collect::<Vec<bool>>();
+        let mut rng = StdRng::seed_from_u64(4);
+        let mut replayed = king::System::new(inputs.clone(), byzantine.clone(), &mut rng);
+        replayed.replay(recording);
+        assert_eq!(first_difference(&recorded, &trace(&mut replayed)), None);
+
+        let mut diverged = king::System::new(inputs, byzantine, &mut rng);
+        assert!(first_difference(&recorded, &trace(&mut diverged)).is_some());
+    }
+}
@@ -0,0 +1,106 @@
+use super::*;
+
+pub struct Byzantine {
+    id: usize,
+    link: LinkInterface,
+    node_count: usize,
+    max_faults: usize,
+    behavior: Behavior,
+    coin: Coin,
+    round: usize,
+}
+
+impl Node for Byzantine {
+    fn exec(&mut self, logger: &mut Logger) {
+        logger.log_actor(self);
+        self.link.borrow_mut().empty_buffer();
+        let phase = self.round / 2;
+        if phase > self.max_faults {
+            return;
+        }
+
+        if self.round.is_multiple_of(2) {
+            self.broadcast(|value| Message::Value(phase, value), logger);
+        } else if king(phase) == self.id {
+            self.broadcast(|value| Message::King(phase, value), logger);
+        }
+        self.round += 1;
+    }
+
+    fn is_honest(&self) -> bool {
+        false
+    }
+
+    fn get_input(&self) -> bool {
+        false
+    }
+
+    fn get_decision(&self) -> Option<bool> {
+        None
+    }
+
+    fn state(&self) -> Vec<(String, String)> {
+        vec![
+            (String::from("round"), self.round.to_string()),
+            (String::from("behavior"), format!("{:?}", self.behavior)),
+        ]
+    }
+
+    fn coin(&self) -> Option<&Coin> {
+        Some(&self.coin)
+    }
+
+    fn coin_mut(&mut self) -> Option<&mut Coin> {
+        Some(&mut self.coin)
+    }
+}
+
+impl Debug for Byzantine {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Byzantine #{}", self.id)
+    }
+}
+
+impl Actor for Byzantine {
+    fn actor_kind(&self) -> &'static str {
+        "Byzantine"
+    }
+
+    fn actor_id(&self) -> Option<usize> {
+        Some(self.id)
+    }
+}
+
+impl Byzantine {
+    pub fn new(
+        id: usize,
+        link: LinkInterface,
+        node_count: usize,
+        max_faults: usize,
+        behavior: Behavior,
+        rng: StdRng,
+    ) -> Self {
+        Byzantine {
+            id,
+            link,
+            node_count,
+            max_faults,
+            behavior,
+            coin: Coin::new(rng),
+            round: 0,
+        }
+    }
+
+    fn broadcast<F>(&mut self, message: F, logger: &mut Logger)
+    where
+        F: Fn(bool) -> Message,
+    {
+        for receiver in 0..self.node_count {
+            let Some(value) = self.behavior.value(receiver, &mut self.coin) else {
+                return;
+            };
+            let id = self.link.borrow_mut().enqueue(receiver, message(value));
+            logger.log_action(&Action::Send(receiver, id, message(value)));
+        }
+    }
+}
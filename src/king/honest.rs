@@ -0,0 +1,176 @@
+use super::*;
+use std::collections::HashSet;
+
+pub struct Honest {
+    id: usize,
+    link: LinkInterface,
+    node_count: usize,
+    max_faults: usize,
+    input: bool,
+    value: bool,
+    majority: bool,
+    multiplicity: usize,
+    round: usize,
+    decision: Option<bool>,
+}
+
+impl Node for Honest {
+    fn exec(&mut self, logger: &mut Logger) {
+        logger.log_actor(self);
+        let inbox = self.link.borrow_mut().empty_buffer();
+        if self.decision.is_some() {
+            return;
+        }
+
+        let phase = self.round / 2;
+        if self.round.is_multiple_of(2) {
+            if phase > 0 {
+                self.adopt_king(phase - 1, &inbox, logger);
+            }
+            if phase == self.max_faults + 1 {
+                logger.log_action(&Action::Decide(self.value));
+                self.decision = Some(self.value);
+                return;
+            }
+            self.broadcast(Message::Value(phase, self.value), logger);
+        } else {
+            self.count_values(phase, &inbox, logger);
+            if king(phase) == self.id {
+                self.broadcast(Message::King(phase, self.majority), logger);
+            }
+        }
+        self.round += 1;
+    }
+
+    fn is_honest(&self) -> bool {
+        true
+    }
+
+    fn get_input(&self) -> bool {
+        self.input
+    }
+
+    fn get_decision(&self) -> Option<bool> {
+        self.decision
+    }
+
+    fn state(&self) -> Vec<(String, String)> {
+        let decision = self
+            .decision
+            .map_or(String::from("⊥"), |d| (d as u8).to_string());
+        vec![
+            (String::from("round"), self.round.to_string()),
+            (String::from("x"), (self.value as u8).to_string()),
+            (String::from("maj"), (self.majority as u8).to_string()),
+            (String::from("mult"), self.multiplicity.to_string()),
+            (String::from("decision"), decision),
+        ]
+    }
+}
+
+impl Debug for Honest {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Honest #{}", self.id)
+    }
+}
+
+impl Actor for Honest {
+    fn actor_kind(&self) -> &'static str {
+        "Honest"
+    }
+
+    fn actor_id(&self) -> Option<usize> {
+        Some(self.id)
+    }
+}
+
+impl Honest {
+    pub fn new(
+        id: usize,
+        link: LinkInterface,
+        node_count: usize,
+        max_faults: usize,
+        input: bool,
+    ) -> Self {
+        Honest {
+            id,
+            link,
+            node_count,
+            max_faults,
+            input,
+            value: input,
+            majority: input,
+            multiplicity: 0,
+            round: 0,
+            decision: None,
+        }
+    }
+
+    fn broadcast(&self, message: Message, logger: &mut Logger) {
+        for receiver in 0..self.node_count {
+            let id = self.link.borrow_mut().enqueue(receiver, message);
+            logger.log_action(&Action::Send(receiver, id, message));
+        }
+    }
+
+    fn count_values(&mut self, phase: Phase, inbox: &[Packet<Message>], logger: &mut Logger) {
+        // A byzantine node only gets its first value of a phase counted.
+        let mut senders = HashSet::new();
+        let mut ones = 0;
+        let mut zeros = 0;
+        for packet in inbox {
+            if let Message::Value(p, value) = packet.content {
+                if p == phase && senders.insert(packet.sender) {
+                    self.link.borrow_mut().receive(packet);
+                    logger.log_action(&Action::Receive(packet.clone()));
+                    if value {
+                        ones += 1
+                    } else {
+                        zeros += 1
+                    }
+                }
+            }
+        }
+
+        self.majority = ones > zeros;
+        self.multiplicity = ones.max(zeros);
+        logger.log_action(&Action::Store(
+            String::from("maj"),
+            format!("{}", self.majority as u8),
+        ));
+        logger.log_action(&Action::Store(
+            String::from("mult"),
+            format!("{}", self.multiplicity),
+        ));
+    }
+
+    fn adopt_king(&mut self, phase: Phase, inbox: &[Packet<Message>], logger: &mut Logger) {
+        let king_value = inbox.iter().find_map(|packet| match packet.content {
+            Message::King(p, value) if p == phase && packet.sender == king(phase) => {
+                self.link.borrow_mut().receive(packet);
+                logger.log_action(&Action::Receive(packet.clone()));
+                Some(value)
+            }
+            _ => None,
+        });
+
+        let threshold = self.node_count / 2 + self.max_faults;
+        let keep = self.multiplicity > threshold;
+        logger.log_action(&Action::Check(
+            String::from("mult > n / 2 + f"),
+            format!("{} > {}", self.multiplicity, threshold),
+            keep,
+        ));
+
+        // A silent king counts as a king sending 0.
+        self.value = if keep {
+            self.majority
+        } else {
+            king_value.unwrap_or(false)
+        };
+        logger.log_action(&Action::Store(
+            String::from("x"),
+            format!("{}", self.value as u8),
+        ));
+    }
+}
@@ -1,74 +1,291 @@
+use network::{Kind, Network, NetworkConfig, Recording};
+use sink::{FileSink, LogSink, NullSink};
+use std::collections::BTreeMap;
 use std::fmt::Debug;
-use std::{fs::File, io::Write};
+use std::io;
+use trace::{Actor, TraceEvent, TraceFormat, Traceable};
 
+pub mod behavior;
+pub mod ben_or;
+pub mod coin;
+pub mod diagram;
+pub mod experiment;
+pub mod king;
 pub mod network;
 pub mod paxos;
+pub mod shiviz;
+pub mod sink;
+pub mod trace;
 
 pub trait System {
+    /// `server_count` are the servers of Paxos, the byzantine nodes of Phase
+    /// King and the crashing nodes of Ben-Or. Counts above `max_servers` are
+    /// clamped to it.
     fn new_rand(node_count: usize, server_count: usize, seed: Option<u64>) -> Self;
-    fn simulate(&mut self, max_rounds: Option<usize>, log: Option<&str>);
+    /// Same as `new_rand`, but on a network configured by `config`.
+    fn new_with_network(
+        node_count: usize,
+        server_count: usize,
+        seed: Option<u64>,
+        config: NetworkConfig,
+    ) -> Self;
+    /// Same as `new_with_network`, but with the inputs and random choices of
+    /// `recording` instead of a seed's, which is then replayed.
+    fn from_recording(
+        node_count: usize,
+        server_count: usize,
+        config: NetworkConfig,
+        recording: Recording,
+    ) -> Result<Self, String>
+    where
+        Self: Sized;
+    /// Largest `server_count` the algorithm takes with `node_count` nodes.
+    fn max_servers(node_count: usize) -> usize
+    where
+        Self: Sized,
+    {
+        node_count
+    }
+    /// Fails if the log could not be written.
+    fn simulate(
+        &mut self,
+        max_rounds: Option<usize>,
+        log: Option<&str>,
+    ) -> io::Result<SimulationReport> {
+        let mut logger = Logger::new(log);
+        let report = self.simulate_with(max_rounds, &mut logger);
+        logger.flush()?;
+        Ok(report)
+    }
+    fn simulate_with(&mut self, max_rounds: Option<usize>, logger: &mut Logger)
+        -> SimulationReport;
+    /// Runs exactly one round and returns what happened in it.
+    fn step(&mut self) -> Vec<TraceEvent> {
+        let mut logger = Logger::capturing();
+        self.step_with(&mut logger);
+        logger.take_events()
+    }
+    fn step_with(&mut self, logger: &mut Logger);
+    fn node_states(&self) -> Vec<NodeState>;
     fn decided(&self) -> bool;
+    /// Records the decisions of the network and the random choices of the
+    /// nodes from now on, see `Network::record`.
+    fn record(&mut self);
+    fn recording(&self) -> Option<Recording>;
+    /// Takes the decisions of the network and the random choices of the
+    /// nodes from a run of a system with the same nodes and inputs.
+    fn replay(&mut self, recording: Recording);
 }
 
+/// Why a simulation stopped.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Termination {
+    Decided,
+    MaxRounds,
+    SafetyViolation,
+}
+
+/// Outcome of `System::simulate`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SimulationReport {
+    pub rounds: usize,
+    pub termination: Termination,
+    /// Distinct values decided by correct nodes, for replicated logs the
+    /// entries of the log.
+    pub decided: Vec<String>,
+    /// Round in which each node first decided, by node id.
+    pub decision_rounds: Vec<Option<usize>>,
+    pub messages_sent: usize,
+    /// Packets that reached their receiver, not counting extra copies.
+    pub messages_delivered: usize,
+    pub messages_duplicated: usize,
+    pub messages_dropped: usize,
+    /// Messages sent per kind, e.g. `Ask` or `Propose`.
+    pub message_counts: BTreeMap<String, usize>,
+}
+
+impl SimulationReport {
+    /// Takes the message statistics from `network`, decisions are left to
+    /// the system.
+    pub fn new<M>(network: &Network<M>, rounds: usize, termination: Termination) -> Self
+    where
+        M: Clone + Debug + Kind + 'static,
+    {
+        SimulationReport {
+            rounds,
+            termination,
+            decided: Vec::new(),
+            decision_rounds: Vec::new(),
+            messages_sent: network.sent(),
+            messages_delivered: network.delivered(),
+            messages_duplicated: network.redelivered(),
+            messages_dropped: network.dropped(),
+            message_counts: network
+                .sent_kinds()
+                .iter()
+                .map(|(kind, count)| (kind.to_string(), *count))
+                .collect(),
+        }
+    }
+}
+
+/// Snapshot of a node between two rounds.
+#[derive(Debug, Clone, PartialEq)]
+pub struct NodeState {
+    pub id: usize,
+    pub kind: &'static str,
+    pub crashed: bool,
+    /// The node's variables as they are written by `store` actions.
+    pub variables: Vec<(String, String)>,
+}
+
+impl NodeState {
+    pub fn new<A: Actor + ?Sized>(
+        node: &A,
+        crashed: bool,
+        variables: Vec<(String, String)>,
+    ) -> Self {
+        NodeState {
+            id: node.actor_id().expect("Nodes have an id"),
+            kind: node.actor_kind(),
+            crashed,
+            variables,
+        }
+    }
+
+    pub fn variable(&self, name: &str) -> Option<&str> {
+        self.variables
+            .iter()
+            .find(|(n, _)| n == name)
+            .map(|(_, v)| v.as_str())
+    }
+}
+
+/// Writes the trace of a run to a `LogSink`. The first I/O error stops the
+/// logging and is returned by `flush`.
 pub struct Logger {
-    file: File,
+    sink: Box<dyn LogSink>,
     logging: bool,
+    format: TraceFormat,
+    round: usize,
+    actor: Option<(&'static str, Option<usize>)>,
+    error: Option<io::Error>,
+    events: Option<Vec<TraceEvent>>,
 }
 
 impl Logger {
+    /// Logs to a timestamped file in `target/logs`, or nowhere for `None`.
+    /// A file that cannot be created is reported by `flush`.
     pub fn new(name: Option<&str>) -> Self {
         match name {
             Some(name) => {
                 let now = chrono::Local::now();
                 let timestamp = now.format("%Y%m%d_%H%M%S").to_string();
                 let path = format!("target/logs/{name}_{timestamp}.log");
-                let file = File::create(path).expect("Path is invalid");
-                Logger {
-                    file,
-                    logging: true,
-                }
+                Self::with_path(&path).unwrap_or_else(|e| {
+                    let mut logger = Self::with_sink(Box::new(NullSink), TraceFormat::Text);
+                    logger.error = Some(e);
+                    logger
+                })
+            }
+            None => {
+                let mut logger = Self::with_sink(Box::new(NullSink), TraceFormat::Text);
+                logger.logging = false;
+                logger
             }
-            None => Logger {
-                file: File::create("target/logs/dummy.log").unwrap(),
-                logging: false,
-            },
+        }
+    }
+
+    pub fn with_sink(sink: Box<dyn LogSink>, format: TraceFormat) -> Self {
+        Logger {
+            sink,
+            logging: true,
+            format,
+            round: 0,
+            actor: None,
+            error: None,
+            events: None,
+        }
+    }
+
+    /// Writes nothing and keeps every action as a `TraceEvent` instead.
+    pub fn capturing() -> Self {
+        let mut logger = Self::new(None);
+        logger.capture();
+        logger
+    }
+
+    /// Keeps every action from now on as a `TraceEvent`, in addition to
+    /// writing it.
+    pub fn capture(&mut self) {
+        self.events.get_or_insert_with(Vec::new);
+    }
+
+    /// The events captured since the last call.
+    pub fn take_events(&mut self) -> Vec<TraceEvent> {
+        self.events.as_mut().map(std::mem::take).unwrap_or_default()
+    }
+
+    /// Logs text to `path`, creating missing directories.
+    pub fn with_path(path: &str) -> io::Result<Self> {
+        Self::with_format(path, TraceFormat::Text)
+    }
+
+    pub fn with_format(path: &str, format: TraceFormat) -> io::Result<Self> {
+        Ok(Self::with_sink(Box::new(FileSink::create(path)?), format))
+    }
+
+    /// Flushes the sink, or returns the first error since the last flush.
+    pub fn flush(&mut self) -> io::Result<()> {
+        match self.error.take() {
+            Some(e) => Err(e),
+            None => self.sink.flush(),
         }
     }
 
     pub fn log_round(&mut self, round: usize) {
-        if !self.logging {
+        self.round = round;
+        if !self.logging || self.format != TraceFormat::Text {
             return;
         }
-        let log_string = format!("Iteration nr: {round}\n");
-        self.file
-            .write_all(log_string.as_bytes())
-            .expect("Couldn't log the round")
+        self.write(&format!("Iteration nr: {round}"))
     }
 
-    pub fn log_actor<Actor>(&mut self, actor: &Actor)
+    pub fn log_actor<A>(&mut self, actor: &A)
     where
-        Actor: Debug,
+        A: Actor + ?Sized,
     {
-        if !self.logging {
+        self.actor = Some((actor.actor_kind(), actor.actor_id()));
+        if !self.logging || self.format != TraceFormat::Text {
             return;
         }
-        let log_string = format!("\t{actor:?}\n");
-        self.file
-            .write_all(log_string.as_bytes())
-            .expect("Couldn't log the actor")
+        self.write(&format!("\t{actor:?}"))
     }
 
-    pub fn log_action<Action>(&mut self, action: &Action)
+    pub fn log_action<A>(&mut self, action: &A)
     where
-        Action: Debug,
+        A: Traceable + ?Sized,
     {
+        if let Some(events) = &mut self.events {
+            events.push(TraceEvent::new(self.round, self.actor, action));
+        }
         if !self.logging {
             return;
         }
 
-        let log_string = format!("\t\t{action:?}\n");
-        self.file
-            .write_all(log_string.as_bytes())
-            .expect("Couldn't log the action")
+        let line = match self.format {
+            TraceFormat::Text => format!("\t\t{action:?}"),
+            TraceFormat::JsonLines => TraceEvent::new(self.round, self.actor, action).to_json(),
+        };
+        self.write(&line)
+    }
+
+    fn write(&mut self, line: &str) {
+        if self.error.is_some() {
+            return;
+        }
+        if let Err(e) = self.sink.write_line(line) {
+            self.error = Some(e);
+        }
     }
 }
@@ -1,33 +1,305 @@
+mod clock;
+mod latency;
+mod queue;
+mod recording;
+mod scheduler;
+
+use crate::trace::{Actor, Field, Traceable};
+pub use clock::VectorClock;
+pub use latency::LatencyModel;
 use rand::{rngs::StdRng, Rng, SeedableRng};
-use std::{cell::RefCell, rc::Rc};
+pub use recording::Recording;
+pub use scheduler::{Fifo, LatencyOrder, RandomOrder, Replay, Scheduler, Starve};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::{
+    cell::{Cell, RefCell},
+    fmt::Debug,
+    rc::Rc,
+};
 
 pub struct Network<M> {
     links: Vec<Rc<RefCell<Link<M>>>>,
-    packets: Vec<(usize, Packet<M>)>, // (ttl, packet)
+    packets: Box<dyn Scheduler<M>>,
+    held: Vec<Packet<M>>,         // packets waiting for a partition to heal
+    unsent_drops: HashSet<usize>, // ids of unsent packets to drop when sent
+    discarded: Vec<Packet<M>>,    // packets dropped by hand, reported next round
     latencies: Option<Vec<Vec<usize>>>,
+    config: NetworkConfig,
+    rng: StdRng,
+    sent: usize,
+    sent_kinds: BTreeMap<&'static str, usize>,
+    delivered: HashSet<usize>, // ids of packets that reached their receiver
+    redelivered: usize,
+    dropped: usize,
+    duplicated: usize,
+    events: Vec<NetworkEvent<M>>,
+    round: usize,
+    recording: Option<Recording>,
+    replaying: Option<Recording>,
 }
 pub struct Link<M> {
     id: usize,
+    next_packet: Rc<Cell<usize>>, // shared by all links of a network
     in_buffer: Vec<Packet<M>>,
     out_buffer: Vec<Packet<M>>,
+    clock: VectorClock,
+}
+
+/// Messages the network can count by kind.
+pub trait Kind {
+    /// Name of the variant, e.g. `Ask` or `Propose`.
+    fn kind(&self) -> &'static str;
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Packet<M> {
+    pub id: usize, // position among all sent packets, copies keep the original's
     pub sender: usize,
     pub receiver: usize,
     pub content: M,
+    /// Clock of the sender when it sent the packet.
+    pub clock: VectorClock,
+}
+
+#[derive(Clone, Debug)]
+pub struct NetworkConfig {
+    pub timing: Timing,
+    pub latency: LatencyModel,
+    pub drop_probability: f64,
+    pub link_drop_probabilities: HashMap<usize, f64>, // link id -> drop probability
+    pub duplicate_probability: f64,
+    pub max_jitter: usize, // extra latency drawn per packet from 0..=max_jitter
+    pub partitions: Vec<Partition>,
 }
 
-impl<M> Network<M> {
+/// `Synchronous` networks deliver every packet in the round it is sent.
+/// `Asynchronous` ones delay packets according to the latency model. A
+/// `PartiallySynchronous` network behaves asynchronously until the global
+/// stabilization time `gst`, but still delivers every packet by round
+/// `max(sent, gst) + delta`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Timing {
+    Synchronous,
+    Asynchronous,
+    PartiallySynchronous { gst: usize, delta: usize },
+}
+
+/// Splits the links into groups that cannot reach each other from round
+/// `start` until round `heal`. Links that are not part of any group stay
+/// connected to everyone.
+#[derive(Clone, Debug)]
+pub struct Partition {
+    pub groups: Vec<Vec<usize>>,
+    pub start: usize,
+    pub heal: Option<usize>,
+    pub policy: PartitionPolicy,
+}
+
+/// What happens to a packet that arrives across an active partition.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum PartitionPolicy {
+    Drop,
+    Hold,
+}
+
+pub enum NetworkEvent<M> {
+    Drop(Packet<M>),
+    Duplicate(Packet<M>),
+    Hold(Packet<M>),
+    Partition(Vec<Vec<usize>>),
+    Heal(Vec<Vec<usize>>),
+}
+
+impl NetworkConfig {
+    pub fn new(asynchronous: bool, max_latency: usize) -> Self {
+        let timing = if asynchronous {
+            Timing::Asynchronous
+        } else {
+            Timing::Synchronous
+        };
+        NetworkConfig {
+            timing,
+            latency: LatencyModel::Uniform { max: max_latency },
+            drop_probability: 0.0,
+            link_drop_probabilities: HashMap::new(),
+            duplicate_probability: 0.0,
+            max_jitter: 0,
+            partitions: Vec::new(),
+        }
+    }
+
+    /// A packet is dropped with the highest probability configured for the
+    /// whole network, its sending link or its receiving link.
+    pub fn drop_probability(&self, sender: usize, receiver: usize) -> f64 {
+        [sender, receiver]
+            .iter()
+            .filter_map(|id| self.link_drop_probabilities.get(id))
+            .fold(self.drop_probability, |max, &p| max.max(p))
+    }
+}
+
+impl Partition {
+    pub fn new(
+        groups: Vec<Vec<usize>>,
+        start: usize,
+        heal: Option<usize>,
+        policy: PartitionPolicy,
+    ) -> Self {
+        if let Some(heal) = heal {
+            assert!(start < heal, "Partition heals before it starts");
+        }
+        Partition {
+            groups,
+            start,
+            heal,
+            policy,
+        }
+    }
+
+    /// Parses groups written as `{0,1} | {2,3,4}`.
+    pub fn parse(
+        spec: &str,
+        start: usize,
+        heal: Option<usize>,
+        policy: PartitionPolicy,
+    ) -> Result<Self, String> {
+        if let Some(heal) = heal.filter(|&heal| heal <= start) {
+            return Err(format!("Partition heals in round {heal} before it starts"));
+        }
+        let mut groups = Vec::new();
+        for group in spec.split('|') {
+            let ids = group
+                .trim()
+                .strip_prefix('{')
+                .and_then(|g| g.strip_suffix('}'))
+                .ok_or(format!("Group {group} is not enclosed in braces"))?;
+            let ids = ids
+                .split(',')
+                .filter(|id| !id.trim().is_empty())
+                .map(|id| {
+                    id.trim()
+                        .parse()
+                        .map_err(|_| format!("Invalid link id {id}"))
+                })
+                .collect::<Result<Vec<usize>, String>>()?;
+            groups.push(ids);
+        }
+        Ok(Partition::new(groups, start, heal, policy))
+    }
+
+    pub fn is_active(&self, round: usize) -> bool {
+        round >= self.start && self.heal.is_none_or(|heal| round < heal)
+    }
+
+    pub fn separates(&self, a: usize, b: usize) -> bool {
+        let group_of = |id| self.groups.iter().position(|g| g.contains(&id));
+        match (group_of(a), group_of(b)) {
+            (Some(a), Some(b)) => a != b,
+            _ => false,
+        }
+    }
+}
+
+fn format_groups(groups: &[Vec<usize>]) -> String {
+    groups
+        .iter()
+        .map(|g| {
+            let ids: Vec<String> = g.iter().map(|id| id.to_string()).collect();
+            format!("{{{}}}", ids.join(","))
+        })
+        .collect::<Vec<String>>()
+        .join(" | ")
+}
+
+impl<M: Debug> Debug for NetworkEvent<M> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Drop(packet) => write!(
+                f,
+                "dropped {:?} from {} to {}",
+                packet.content, packet.sender, packet.receiver
+            ),
+            Self::Duplicate(packet) => write!(
+                f,
+                "duplicated {:?} from {} to {}",
+                packet.content, packet.sender, packet.receiver
+            ),
+            Self::Hold(packet) => write!(
+                f,
+                "held {:?} from {} to {}",
+                packet.content, packet.sender, packet.receiver
+            ),
+            Self::Partition(groups) => write!(f, "partition {}", format_groups(groups)),
+            Self::Heal(groups) => write!(f, "heal {}", format_groups(groups)),
+        }
+    }
+}
+
+impl<M: Debug> Traceable for NetworkEvent<M> {
+    fn action(&self) -> &'static str {
+        match self {
+            Self::Drop(_) => "drop",
+            Self::Duplicate(_) => "duplicate",
+            Self::Hold(_) => "hold",
+            Self::Partition(_) => "partition",
+            Self::Heal(_) => "heal",
+        }
+    }
+
+    fn fields(&self) -> Vec<(&'static str, Field)> {
+        match self {
+            Self::Drop(packet) | Self::Duplicate(packet) | Self::Hold(packet) => vec![
+                ("packet", packet.id.into()),
+                ("sender", packet.sender.into()),
+                ("receiver", packet.receiver.into()),
+                ("message", Field::debug(&packet.content)),
+            ],
+            Self::Partition(groups) | Self::Heal(groups) => {
+                vec![("groups", format_groups(groups).into())]
+            }
+        }
+    }
+}
+
+impl<M> Debug for Network<M> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Network")
+    }
+}
+
+impl<M> Actor for Network<M> {
+    fn actor_kind(&self) -> &'static str {
+        "Network"
+    }
+
+    fn actor_id(&self) -> Option<usize> {
+        None
+    }
+}
+
+impl<M: Clone + Debug + Kind + 'static> Network<M> {
     pub fn new(
         asnychronous: bool,
         link_count: usize,
         seed: Option<u64>,
         max_latency: usize,
     ) -> Self {
+        Self::with_config(
+            link_count,
+            seed,
+            NetworkConfig::new(asnychronous, max_latency),
+        )
+    }
+
+    pub fn with_config(link_count: usize, seed: Option<u64>, config: NetworkConfig) -> Self {
+        let probabilities = config.link_drop_probabilities.values();
+        for p in probabilities.chain([&config.drop_probability, &config.duplicate_probability]) {
+            assert!((0.0..=1.0).contains(p), "Invalid probability {p}");
+        }
+
+        let next_packet = Rc::new(Cell::new(0));
         let links = (0..link_count)
-            .map(|id| Rc::new(RefCell::new(Link::new(id))))
+            .map(|id| Rc::new(RefCell::new(Link::new(id, next_packet.clone()))))
             .collect();
 
         let mut rng = match seed {
@@ -35,16 +307,31 @@ impl<M> Network<M> {
             None => StdRng::from_entropy(),
         };
 
-        let latencies = if asnychronous {
-            Some(Self::rand_latencies(link_count, max_latency, &mut rng))
+        let latencies = if config.timing != Timing::Synchronous {
+            config.latency.matrix(link_count, &mut rng)
         } else {
             None
         };
 
         Network {
             links,
-            packets: Vec::new(),
+            packets: Box::new(LatencyOrder::new()),
+            held: Vec::new(),
+            unsent_drops: HashSet::new(),
+            discarded: Vec::new(),
             latencies,
+            config,
+            rng,
+            sent: 0,
+            sent_kinds: BTreeMap::new(),
+            delivered: HashSet::new(),
+            redelivered: 0,
+            dropped: 0,
+            duplicated: 0,
+            events: Vec::new(),
+            round: 0,
+            recording: None,
+            replaying: None,
         }
     }
 
@@ -55,34 +342,260 @@ impl<M> Network<M> {
             .clone()
     }
 
-    pub fn get_latency(&self, message: &Packet<M>) -> usize {
+    pub fn get_latency(&mut self, message: &Packet<M>) -> usize {
+        if self.config.timing == Timing::Synchronous || message.sender == message.receiver {
+            return 0;
+        }
         match &self.latencies {
             Some(latencies) => *latencies
                 .get(message.sender)
                 .expect(format!("{} not in range for valid ids", message.sender).as_str())
                 .get(message.receiver)
                 .expect(format!("{} not in range for valid ids", message.receiver).as_str()),
-            None => 0,
+            None => self
+                .config
+                .latency
+                .sample(message.sender, message.receiver, &mut self.rng),
         }
     }
 
-    fn rand_latencies(link_count: usize, max_latency: usize, rng: &mut StdRng) -> Vec<Vec<usize>> {
-        let mut latencies = vec![vec![0; link_count]; link_count];
-        for (i, vec) in latencies.iter_mut().enumerate() {
-            for (j, val) in vec.iter_mut().enumerate() {
-                if i == j {
-                    *val = 0
-                } else {
-                    *val = rng.gen_range(0..max_latency)
-                }
-            }
+    /// Number of packets nodes handed to the network since it was created.
+    pub fn sent(&self) -> usize {
+        self.sent
+    }
+
+    /// Number of packets sent per kind of message.
+    pub fn sent_kinds(&self) -> &BTreeMap<&'static str, usize> {
+        &self.sent_kinds
+    }
+
+    /// Number of sent packets that reached their receiver's in buffer, at
+    /// most `sent()`.
+    pub fn delivered(&self) -> usize {
+        self.delivered.len()
+    }
+
+    /// Number of copies that reached the receiver after the first one.
+    pub fn redelivered(&self) -> usize {
+        self.redelivered
+    }
+
+    /// Number of packets dropped since the network was created.
+    pub fn dropped(&self) -> usize {
+        self.dropped
+    }
+
+    /// Number of additional copies injected since the network was created.
+    pub fn duplicated(&self) -> usize {
+        self.duplicated
+    }
+
+    /// Replaces the default `LatencyOrder` scheduler. Only allowed while
+    /// no packet is in flight.
+    pub fn set_scheduler(&mut self, scheduler: Box<dyn Scheduler<M>>) {
+        assert!(
+            self.packets.is_empty(),
+            "Cannot switch schedulers in flight"
+        );
+        self.packets = scheduler;
+    }
+
+    /// Records every drop, duplication and delivery from now on.
+    pub fn record(&mut self) {
+        self.recording = Some(Recording::new());
+    }
+
+    pub fn recording(&self) -> Option<&Recording> {
+        self.recording.as_ref()
+    }
+
+    /// Takes drops, duplications and deliveries from `recording` instead of
+    /// the rng, the latency model and the scheduler. Only allowed while no
+    /// packet is in flight.
+    pub fn replay(&mut self, recording: Recording) {
+        self.set_scheduler(Box::new(Replay::new(recording.deliveries.clone())));
+        self.replaying = Some(recording);
+    }
+
+    /// Number of rounds exchanged so far.
+    pub fn round(&self) -> usize {
+        self.round
+    }
+
+    /// Number of packets that are sent but not yet delivered.
+    pub fn in_flight(&self) -> usize {
+        self.packets.len() + self.held.len()
+    }
+
+    /// Every packet that is sent but not yet delivered with the round it is
+    /// due in, `None` if the scheduler or a partition has not decided yet.
+    pub fn in_flight_packets(&self) -> Vec<(Option<usize>, Packet<M>)> {
+        let pending = self.packets.pending().into_iter();
+        let held = self.held.iter().map(|packet| (None, packet));
+        let mut packets: Vec<_> = pending
+            .chain(held)
+            .map(|(due, packet)| (due, packet.clone()))
+            .collect();
+        packets.extend(
+            self.unsent_packets()
+                .filter(|packet| !self.unsent_drops.contains(&packet.id))
+                .map(|packet| (None, packet)),
+        );
+        packets
+    }
+
+    /// Drops the in-flight packet `id` as if the network lost it, reported
+    /// with the events of the next round. Returns whether there was such a
+    /// packet.
+    pub fn drop_packet(&mut self, id: usize) -> bool {
+        let dropped = if id >= self.sent {
+            let unsent = self.unsent_packets().any(|packet| packet.id == id);
+            unsent && self.unsent_drops.insert(id)
+        } else {
+            self.discard(id)
+        };
+        if let (true, Some(recording)) = (dropped, &mut self.recording) {
+            recording.discarded.entry(self.round).or_default().push(id);
+        }
+        dropped
+    }
+
+    /// Delivers the in-flight packet `id` `rounds` rounds after it was due.
+    /// Returns false if the scheduler holds no such packet or does not go by
+    /// due rounds.
+    pub fn delay_packet(&mut self, id: usize, rounds: usize) -> bool {
+        self.packets.delay(id, rounds, self.round)
+    }
+
+    /// Round of the next delivery, partition change or manual drop, if
+    /// anything is pending. Unsent packets are collected and drops by hand
+    /// reported in the current round.
+    pub fn next_event(&self) -> Option<usize> {
+        let unsent = self.links.iter().any(|l| !l.borrow().out_buffer.is_empty());
+        if unsent || !self.discarded.is_empty() {
+            return Some(self.round);
         }
-        latencies
+        let replayed_discard = self
+            .replaying
+            .iter()
+            .flat_map(|replaying| replaying.discarded.range(self.round..).next())
+            .map(|(&round, _)| round);
+        let partition_changes = self
+            .config
+            .partitions
+            .iter()
+            .flat_map(|p| [Some(p.start), p.heal])
+            .flatten()
+            .filter(|&round| round >= self.round);
+        self.packets
+            .next_delivery(self.round)
+            .into_iter()
+            .chain(partition_changes)
+            .chain(replayed_discard)
+            .min()
+    }
+
+    /// Jumps to `round` without exchanging anything in between. Only allowed
+    /// if nothing happens before `round`.
+    pub fn skip_to(&mut self, round: usize) {
+        assert!(round >= self.round, "Cannot skip backwards in time");
+        assert!(
+            self.next_event().is_none_or(|next| next >= round),
+            "Cannot skip over pending events"
+        );
+        self.round = round;
+    }
+
+    /// Events of the last call to `exchange_messages`.
+    pub fn events(&self) -> &[NetworkEvent<M>] {
+        &self.events
     }
 
     pub fn exchange_messages(&mut self) {
+        self.events.clear();
+        self.replay_discards();
+        let discarded = self.discarded.drain(..).map(NetworkEvent::Drop);
+        self.events.extend(discarded);
+        self.update_partitions();
         self.collect_messages();
         self.deliver_messages();
+        self.round += 1;
+    }
+
+    /// Drops the packets that were dropped by hand before this round.
+    fn replay_discards(&mut self) {
+        let Some(replaying) = &self.replaying else {
+            return;
+        };
+        let ids = replaying.discarded.get(&self.round).cloned();
+        for &id in ids.iter().flatten() {
+            if id >= self.sent {
+                self.unsent_drops.insert(id);
+            } else {
+                self.discard(id);
+            }
+        }
+        if let (Some(ids), Some(recording)) = (ids, &mut self.recording) {
+            recording.discarded.insert(self.round, ids);
+        }
+    }
+
+    /// Takes the packet `id` from the partitions or the scheduler.
+    fn discard(&mut self, id: usize) -> bool {
+        let packet = match self.held.iter().position(|p| p.id == id) {
+            Some(index) => Some(self.held.remove(index)),
+            None => self.packets.remove(id),
+        };
+        let Some(packet) = packet else {
+            return false;
+        };
+        self.dropped += 1;
+        self.discarded.push(packet);
+        true
+    }
+
+    fn update_partitions(&mut self) {
+        for partition in self.config.partitions.iter() {
+            if partition.start == self.round {
+                let groups = partition.groups.clone();
+                self.events.push(NetworkEvent::Partition(groups));
+            }
+            if partition.heal == Some(self.round) {
+                let groups = partition.groups.clone();
+                self.events.push(NetworkEvent::Heal(groups));
+            }
+        }
+
+        let (cut, healed) = std::mem::take(&mut self.held)
+            .into_iter()
+            .partition(|p| self.cut(p.sender, p.receiver).is_some());
+        self.held = cut;
+        for packet in healed {
+            self.push_to_link(packet);
+        }
+    }
+
+    fn cut(&self, sender: usize, receiver: usize) -> Option<PartitionPolicy> {
+        let mut active = self
+            .config
+            .partitions
+            .iter()
+            .filter(|p| p.is_active(self.round) && p.separates(sender, receiver));
+        // Dropping wins over holding if several partitions cut the same link.
+        let mut policy = active.next()?.policy;
+        for partition in active {
+            if partition.policy == PartitionPolicy::Drop {
+                policy = PartitionPolicy::Drop;
+            }
+        }
+        Some(policy)
+    }
+
+    /// Packets still in the out buffers.
+    fn unsent_packets(&self) -> impl Iterator<Item = Packet<M>> + '_ {
+        self.links
+            .iter()
+            .flat_map(|link| link.borrow().out_buffer.clone())
     }
 
     fn collect_messages(&mut self) {
@@ -90,57 +603,167 @@ impl<M> Network<M> {
         for link in self.links.iter_mut() {
             packets.append(&mut link.borrow_mut().out_buffer.drain(..).collect());
         }
-        self.packets.append(
-            &mut packets
-                .into_iter()
-                .map(|p| (self.get_latency(&p), p))
-                .collect(),
-        )
+
+        self.sent += packets.len();
+        for packet in packets {
+            let id = packet.id;
+            *self.sent_kinds.entry(packet.content.kind()).or_default() += 1;
+
+            if self.unsent_drops.remove(&id) {
+                self.dropped += 1;
+                self.events.push(NetworkEvent::Drop(packet));
+                continue;
+            }
+            if self.is_dropped(&packet) {
+                if let Some(recording) = &mut self.recording {
+                    recording.dropped.insert(id);
+                }
+                self.dropped += 1;
+                self.events.push(NetworkEvent::Drop(packet));
+                continue;
+            }
+
+            if self.is_duplicated(id) {
+                if let Some(recording) = &mut self.recording {
+                    recording.duplicated.insert(id);
+                }
+                self.duplicated += 1;
+                self.events.push(NetworkEvent::Duplicate(packet.clone()));
+                let time = self.delivery_time(&packet);
+                self.packets.push(time, packet.clone());
+            }
+            let time = self.delivery_time(&packet);
+            self.packets.push(time, packet)
+        }
+    }
+
+    fn delivery_time(&mut self, packet: &Packet<M>) -> usize {
+        let latency = self.get_latency(packet).saturating_add(self.jitter());
+        let time = self.round.saturating_add(latency);
+        match self.config.timing {
+            Timing::PartiallySynchronous { gst, delta } => time.min(self.round.max(gst) + delta),
+            _ => time,
+        }
+    }
+
+    // The rng is only consulted for enabled faults, so seeded runs without
+    // them stay identical to the ones before.
+    fn is_dropped(&mut self, packet: &Packet<M>) -> bool {
+        if let Some(replaying) = &self.replaying {
+            return replaying.dropped.contains(&packet.id);
+        }
+        let p = self.config.drop_probability(packet.sender, packet.receiver);
+        p > 0.0 && self.rng.gen_bool(p)
+    }
+
+    fn is_duplicated(&mut self, id: usize) -> bool {
+        if let Some(replaying) = &self.replaying {
+            return replaying.duplicated.contains(&id);
+        }
+        let p = self.config.duplicate_probability;
+        p > 0.0 && self.rng.gen_bool(p)
+    }
+
+    fn jitter(&mut self) -> usize {
+        match self.config.max_jitter {
+            0 => 0,
+            max => self.rng.gen_range(0..=max),
+        }
     }
 
     fn deliver_messages(&mut self) {
-        let mut remaining = Vec::new();
-        for (age, message) in self.packets.drain(..) {
-            if age == 0 {
-                let receiver = message.receiver;
-                self.links
-                    .get(receiver)
-                    .expect(format!("Invalid receiver id {}", receiver).as_str())
-                    .borrow_mut()
-                    .in_buffer
-                    .push(message);
-            } else {
-                remaining.push((age - 1, message))
+        let messages = self.packets.deliver(self.round, &mut self.rng);
+        if let Some(recording) = &mut self.recording {
+            if !messages.is_empty() {
+                let ids = messages.iter().map(|m| m.id).collect();
+                recording.deliveries.insert(self.round, ids);
+            }
+        }
+        for message in messages {
+            match self.cut(message.sender, message.receiver) {
+                Some(PartitionPolicy::Drop) => {
+                    self.dropped += 1;
+                    self.events.push(NetworkEvent::Drop(message));
+                }
+                Some(PartitionPolicy::Hold) => {
+                    self.events.push(NetworkEvent::Hold(message.clone()));
+                    self.held.push(message);
+                }
+                None => self.push_to_link(message),
             }
         }
-        self.packets = remaining
+    }
+
+    fn push_to_link(&mut self, message: Packet<M>) {
+        if !self.delivered.insert(message.id) {
+            self.redelivered += 1;
+        }
+        let receiver = message.receiver;
+        self.links
+            .get(receiver)
+            .expect(format!("Invalid receiver id {}", receiver).as_str())
+            .borrow_mut()
+            .in_buffer
+            .push(message);
     }
 }
 
 impl<M> Link<M> {
-    pub fn new(id: usize) -> Self {
+    /// Packets are numbered by `next_packet` in the order they are sent.
+    pub fn new(id: usize, next_packet: Rc<Cell<usize>>) -> Self {
         Link {
             id,
+            next_packet,
             in_buffer: Vec::new(),
             out_buffer: Vec::new(),
+            clock: VectorClock::new(),
         }
     }
 
-    pub fn enqueue(&mut self, receiver: usize, message: M) {
+    /// Sending is an event of the node, so it ticks the clock the packet
+    /// carries. Returns the id of the packet.
+    pub fn enqueue(&mut self, receiver: usize, message: M) -> usize {
+        let id = self.next_packet.get();
+        self.next_packet.set(id + 1);
+        self.clock.tick(self.id);
         self.out_buffer.push(Packet {
+            id,
             sender: self.id,
             receiver,
             content: message,
-        })
+            clock: self.clock.clone(),
+        });
+        id
     }
 
+    /// Takes every packet that arrived. Only the ones the node passes to
+    /// `receive` count as received, so discarded packets leave no trace in
+    /// the clock.
     pub fn empty_buffer(&mut self) -> Vec<Packet<M>> {
-        self.in_buffer.drain(..).into_iter().collect()
+        self.in_buffer.drain(..).collect()
+    }
+
+    /// Receiving a packet is an event that merges the packet's clock.
+    pub fn receive(&mut self, packet: &Packet<M>) {
+        self.clock.merge(&packet.clock);
+        self.clock.tick(self.id);
+    }
+
+    /// Clock of the node after its last send or receive.
+    pub fn clock(&self) -> &VectorClock {
+        &self.clock
     }
 }
 
 mod test {
 
+    #[cfg(test)]
+    impl super::Kind for usize {
+        fn kind(&self) -> &'static str {
+            "usize"
+        }
+    }
+
     #[test]
     fn simple_sync_system_tests() {
         use super::Network;
@@ -188,4 +811,385 @@ mod test {
         assert_eq!(messages0, vec![69]);
         assert_eq!(messages1, vec![42]);
     }
+
+    #[test]
+    fn lossy_network_drops_everything() {
+        use super::{Network, NetworkConfig};
+
+        let mut config = NetworkConfig::new(false, 0);
+        config.drop_probability = 1.0;
+        let mut network = Network::<usize>::with_config(3, Some(7), config);
+        for id in 0..3 {
+            network
+                .get_link_ref(id)
+                .borrow_mut()
+                .enqueue((id + 1) % 3, id);
+        }
+        network.exchange_messages();
+        assert_eq!(network.sent(), 3);
+        assert_eq!(network.delivered(), 0);
+        assert_eq!(network.dropped(), 3);
+        assert_eq!(network.events().len(), 3);
+        for id in 0..3 {
+            assert!(network
+                .get_link_ref(id)
+                .borrow_mut()
+                .empty_buffer()
+                .is_empty());
+        }
+    }
+
+    #[test]
+    fn lossy_link_only_affects_its_packets() {
+        use super::{Network, NetworkConfig};
+
+        let mut config = NetworkConfig::new(false, 0);
+        config.link_drop_probabilities.insert(2, 1.0);
+        let mut network = Network::<usize>::with_config(3, Some(7), config);
+        network.get_link_ref(0).borrow_mut().enqueue(1, 1);
+        network.get_link_ref(0).borrow_mut().enqueue(2, 2);
+        network.get_link_ref(2).borrow_mut().enqueue(1, 3);
+        network.exchange_messages();
+        assert_eq!(network.dropped(), 2);
+        let received: Vec<usize> = network
+            .get_link_ref(1)
+            .borrow_mut()
+            .empty_buffer()
+            .iter()
+            .map(|p| p.content)
+            .collect();
+        assert_eq!(received, vec![1]);
+    }
+
+    #[test]
+    fn duplicating_network_delivers_twice() {
+        use super::{Network, NetworkConfig};
+
+        let mut config = NetworkConfig::new(false, 0);
+        config.duplicate_probability = 1.0;
+        let mut network = Network::<usize>::with_config(2, Some(7), config);
+        network.get_link_ref(0).borrow_mut().enqueue(1, 42);
+        network.exchange_messages();
+        assert_eq!(network.duplicated(), 1);
+        assert_eq!((network.delivered(), network.redelivered()), (1, 1));
+        let received: Vec<usize> = network
+            .get_link_ref(1)
+            .borrow_mut()
+            .empty_buffer()
+            .iter()
+            .map(|p| p.content)
+            .collect();
+        assert_eq!(received, vec![42, 42]);
+    }
+
+    #[test]
+    fn in_flight_packets_can_be_dropped_and_delayed() {
+        use super::{LatencyModel, Network, NetworkConfig};
+
+        let mut config = NetworkConfig::new(true, 0);
+        config.latency = LatencyModel::Constant(2);
+        let mut network = Network::<usize>::with_config(2, Some(7), config);
+        for content in 10..13 {
+            network.get_link_ref(0).borrow_mut().enqueue(1, content);
+        }
+        network.exchange_messages();
+        let in_flight: Vec<(Option<usize>, usize)> = network
+            .in_flight_packets()
+            .into_iter()
+            .map(|(due, p)| (due, p.id))
+            .collect();
+        assert_eq!(in_flight, vec![(Some(2), 0), (Some(2), 1), (Some(2), 2)]);
+        assert!(network.drop_packet(1));
+        assert!(!network.drop_packet(1));
+        assert!(network.delay_packet(0, 3));
+        assert_eq!(network.dropped(), 1);
+
+        let receiver = network.get_link_ref(1);
+        let mut arrivals = Vec::new();
+        for _ in 1..6 {
+            let round = network.round();
+            network.exchange_messages();
+            for packet in receiver.borrow_mut().empty_buffer() {
+                arrivals.push((packet.content, round));
+            }
+        }
+        assert_eq!(arrivals, vec![(12, 2), (10, 5)]);
+    }
+
+    #[test]
+    fn unsent_packets_can_be_dropped() {
+        use super::Network;
+
+        let mut network = Network::<usize>::new(false, 2, Some(7), 0);
+        network.get_link_ref(0).borrow_mut().enqueue(1, 10);
+        network.get_link_ref(1).borrow_mut().enqueue(0, 11);
+        network.get_link_ref(0).borrow_mut().enqueue(1, 12);
+        let unsent: Vec<(Option<usize>, usize, usize)> = network
+            .in_flight_packets()
+            .into_iter()
+            .map(|(due, p)| (due, p.id, p.content))
+            .collect();
+        assert_eq!(unsent, vec![(None, 0, 10), (None, 2, 12), (None, 1, 11)]);
+        assert!(network.drop_packet(2));
+        assert!(!network.drop_packet(2));
+        assert!(!network.drop_packet(3));
+        assert_eq!(network.in_flight_packets().len(), 2);
+
+        network.exchange_messages();
+        assert_eq!((network.sent(), network.dropped()), (3, 1));
+        let received = network.get_link_ref(1).borrow_mut().empty_buffer();
+        assert_eq!(
+            received.iter().map(|p| p.content).collect::<Vec<_>>(),
+            vec![10]
+        );
+    }
+
+    #[test]
+    fn manual_drops_are_reported_and_replayed() {
+        use super::{LatencyModel, Network, NetworkConfig, NetworkEvent};
+
+        let run = |replay: Option<super::Recording>| {
+            let mut config = NetworkConfig::new(true, 0);
+            config.latency = LatencyModel::Constant(4);
+            let mut network = Network::<usize>::with_config(2, Some(7), config);
+            let recording = replay.is_none();
+            match replay {
+                Some(recording) => network.replay(recording),
+                None => network.record(),
+            }
+            let mut arrivals = Vec::new();
+            for round in 0..8 {
+                network.get_link_ref(0).borrow_mut().enqueue(1, round);
+                // The packet 0 is scheduled, the packet 2 is not sent yet.
+                if round == 2 && recording {
+                    assert!(network.drop_packet(0));
+                    assert!(network.drop_packet(2));
+                }
+                network.exchange_messages();
+                let drops: Vec<usize> = network
+                    .events()
+                    .iter()
+                    .filter_map(|event| match event {
+                        NetworkEvent::Drop(packet) => Some(packet.id),
+                        _ => None,
+                    })
+                    .collect();
+                assert_eq!(drops.is_empty(), round != 2, "{round}");
+                let received = network.get_link_ref(1).borrow_mut().empty_buffer();
+                arrivals.extend(received.into_iter().map(|p| (p.id, round)));
+            }
+            (arrivals, network.recording().cloned())
+        };
+
+        let (arrivals, recording) = run(None);
+        let recording = recording.unwrap();
+        assert_eq!(recording.discarded.get(&2), Some(&vec![0, 2]));
+        assert!(recording.dropped.is_empty());
+        assert_eq!(arrivals, vec![(1, 5), (3, 7)]);
+        let (replayed, _) = run(Some(recording.clone()));
+        assert_eq!(replayed, arrivals);
+    }
+
+    #[test]
+    fn manual_drops_are_next_events() {
+        use super::{LatencyModel, Network, NetworkConfig, Recording};
+
+        let mut config = NetworkConfig::new(true, 0);
+        config.latency = LatencyModel::Constant(10);
+        let mut network = Network::<usize>::with_config(2, Some(7), config.clone());
+        network.get_link_ref(0).borrow_mut().enqueue(1, 1);
+        network.exchange_messages();
+        assert_eq!(network.next_event(), Some(10));
+        assert!(network.drop_packet(0));
+        assert_eq!(network.next_event(), Some(1));
+
+        let mut replay = Network::<usize>::with_config(2, Some(7), config);
+        let mut recording = Recording::new();
+        recording.discarded.insert(4, vec![0]);
+        replay.replay(recording);
+        replay.get_link_ref(0).borrow_mut().enqueue(1, 1);
+        replay.exchange_messages();
+        assert_eq!(replay.next_event(), Some(4));
+        replay.skip_to(4);
+        replay.exchange_messages();
+        assert_eq!(replay.dropped(), 1);
+        assert_eq!(replay.next_event(), None);
+    }
+
+    #[test]
+    fn packets_carry_causal_clocks() {
+        use super::Network;
+
+        let mut network = Network::<usize>::new(false, 3, None, 0);
+        let links: Vec<_> = (0..3).map(|id| network.get_link_ref(id)).collect();
+        links[0].borrow_mut().enqueue(1, 1);
+        links[2].borrow_mut().enqueue(1, 2);
+        network.exchange_messages();
+        let received = links[1].borrow_mut().empty_buffer();
+        assert!(received[0].clock.concurrent(&received[1].clock));
+        // Packets the node discards do not count as received.
+        assert_eq!(links[1].borrow().clock().get(1), 0);
+        received
+            .iter()
+            .for_each(|p| links[1].borrow_mut().receive(p));
+        links[1].borrow_mut().enqueue(0, 3);
+        network.exchange_messages();
+        let reply = links[0].borrow_mut().empty_buffer().remove(0);
+        links[0].borrow_mut().receive(&reply);
+        assert!(received
+            .iter()
+            .all(|p| p.clock.happened_before(&reply.clock)));
+        assert_eq!(reply.clock.get(1), 3);
+        assert_eq!(links[0].borrow().clock().get(0), 2);
+    }
+
+    #[test]
+    fn jitter_reorders_deterministically() {
+        use super::{Network, NetworkConfig};
+
+        let run = |seed| {
+            let mut config = NetworkConfig::new(false, 0);
+            config.max_jitter = 20;
+            let mut network = Network::<usize>::with_config(2, Some(seed), config);
+            let sender = network.get_link_ref(0);
+            let receiver = network.get_link_ref(1);
+            let mut received = Vec::new();
+            for i in 0..10 {
+                sender.borrow_mut().enqueue(1, i);
+                network.exchange_messages();
+                received.extend(
+                    receiver
+                        .borrow_mut()
+                        .empty_buffer()
+                        .iter()
+                        .map(|p| p.content),
+                );
+            }
+            while received.len() < 10 {
+                network.exchange_messages();
+                received.extend(
+                    receiver
+                        .borrow_mut()
+                        .empty_buffer()
+                        .iter()
+                        .map(|p| p.content),
+                );
+            }
+            received
+        };
+
+        let received = run(3);
+        assert_eq!(received, run(3));
+        assert!(received.windows(2).any(|w| w[0] > w[1]));
+    }
+
+    #[test]
+    fn partition_parses_groups() {
+        use super::{Partition, PartitionPolicy};
+
+        let partition = Partition::parse("{0,1} | {2, 3,4}", 1, Some(5), PartitionPolicy::Drop);
+        assert_eq!(partition.unwrap().groups, vec![vec![0, 1], vec![2, 3, 4]]);
+        assert!(Partition::parse("0,1 | {2}", 1, None, PartitionPolicy::Drop).is_err());
+        assert!(Partition::parse("{0,x}", 1, None, PartitionPolicy::Drop).is_err());
+        assert!(Partition::parse("{0} | {1}", 5, Some(3), PartitionPolicy::Drop).is_err());
+    }
+
+    #[test]
+    fn partition_drops_across_cut() {
+        use super::{Network, NetworkConfig, Partition, PartitionPolicy};
+
+        let mut config = NetworkConfig::new(false, 0);
+        let partition = Partition::parse("{0} | {1}", 0, Some(2), PartitionPolicy::Drop).unwrap();
+        config.partitions.push(partition);
+        let mut network = Network::<usize>::with_config(3, None, config);
+        let receiver = network.get_link_ref(1);
+        network.get_link_ref(0).borrow_mut().enqueue(1, 1);
+        network.get_link_ref(2).borrow_mut().enqueue(1, 2);
+        network.exchange_messages();
+        let received: Vec<usize> = receiver
+            .borrow_mut()
+            .empty_buffer()
+            .iter()
+            .map(|p| p.content)
+            .collect();
+        assert_eq!(received, vec![2]);
+        assert_eq!(network.dropped(), 1);
+
+        network.exchange_messages();
+        network.get_link_ref(0).borrow_mut().enqueue(1, 3);
+        network.exchange_messages();
+        let received: Vec<usize> = receiver
+            .borrow_mut()
+            .empty_buffer()
+            .iter()
+            .map(|p| p.content)
+            .collect();
+        assert_eq!(received, vec![3]);
+    }
+
+    #[test]
+    fn partition_holds_until_heal() {
+        use super::{Network, NetworkConfig, Partition, PartitionPolicy};
+
+        let mut config = NetworkConfig::new(false, 0);
+        let partition = Partition::parse("{0} | {1}", 0, Some(3), PartitionPolicy::Hold).unwrap();
+        config.partitions.push(partition);
+        let mut network = Network::<usize>::with_config(2, None, config);
+        let receiver = network.get_link_ref(1);
+        network.get_link_ref(0).borrow_mut().enqueue(1, 42);
+        for _ in 0..3 {
+            network.exchange_messages();
+            assert!(receiver.borrow_mut().empty_buffer().is_empty());
+        }
+        network.exchange_messages();
+        let received: Vec<usize> = receiver
+            .borrow_mut()
+            .empty_buffer()
+            .iter()
+            .map(|p| p.content)
+            .collect();
+        assert_eq!(received, vec![42]);
+        assert_eq!(network.dropped(), 0);
+    }
+
+    #[test]
+    fn constant_latency_model() {
+        use super::{LatencyModel, Network, NetworkConfig};
+
+        let mut config = NetworkConfig::new(true, 0);
+        config.latency = LatencyModel::Constant(3);
+        let mut network = Network::<usize>::with_config(2, None, config);
+        let receiver = network.get_link_ref(1);
+        network.get_link_ref(0).borrow_mut().enqueue(1, 42);
+        for _ in 0..3 {
+            network.exchange_messages();
+            assert!(receiver.borrow_mut().empty_buffer().is_empty());
+        }
+        network.exchange_messages();
+        assert_eq!(receiver.borrow_mut().empty_buffer().len(), 1);
+    }
+
+    #[test]
+    fn partial_synchrony_bounds_latency_after_gst() {
+        use super::{LatencyModel, Network, NetworkConfig, Timing};
+
+        let mut config = NetworkConfig::new(true, 0);
+        config.latency = LatencyModel::Constant(1000);
+        config.timing = Timing::PartiallySynchronous { gst: 10, delta: 2 };
+        let mut network = Network::<usize>::with_config(2, None, config);
+        let sender = network.get_link_ref(0);
+        let receiver = network.get_link_ref(1);
+        let mut arrivals = Vec::new();
+        for round in 0..20 {
+            if round == 0 || round == 15 {
+                sender.borrow_mut().enqueue(1, round);
+            }
+            network.exchange_messages();
+            for packet in receiver.borrow_mut().empty_buffer() {
+                arrivals.push((packet.content, round));
+            }
+        }
+        // Sent before GST: delivered at GST + delta, afterwards within delta.
+        assert_eq!(arrivals, vec![(0, 12), (15, 17)]);
+    }
 }
@@ -0,0 +1,84 @@
+use std::collections::BTreeMap;
+
+/// Number of events per node that causally precede an event. Nodes without
+/// an entry count as zero.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct VectorClock {
+    entries: BTreeMap<usize, usize>,
+}
+
+impl VectorClock {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn get(&self, node: usize) -> usize {
+        self.entries.get(&node).copied().unwrap_or(0)
+    }
+
+    /// Counts a new event of `node`.
+    pub fn tick(&mut self, node: usize) {
+        *self.entries.entry(node).or_insert(0) += 1;
+    }
+
+    /// Takes the maximum of both clocks for every node.
+    pub fn merge(&mut self, other: &VectorClock) {
+        for (&node, &count) in &other.entries {
+            let entry = self.entries.entry(node).or_insert(0);
+            *entry = count.max(*entry);
+        }
+    }
+
+    /// Whether the event of `self` happened before the one of `other`.
+    pub fn happened_before(&self, other: &VectorClock) -> bool {
+        // Entries are never zero, so a different clock is larger somewhere.
+        self.entries
+            .iter()
+            .all(|(&node, &count)| count <= other.get(node))
+            && self != other
+    }
+
+    /// Neither event happened before the other.
+    pub fn concurrent(&self, other: &VectorClock) -> bool {
+        self != other && !self.happened_before(other) && !other.happened_before(self)
+    }
+
+    /// A JSON object from the name of every node to its count, e.g.
+    /// `{"Server0":2,"Client1":1}`.
+    pub fn to_json<F: Fn(usize) -> String>(&self, name: F) -> String {
+        let entries: Vec<String> = self
+            .entries
+            .iter()
+            .map(|(&node, count)| format!("\"{}\":{count}", name(node)))
+            .collect();
+        format!("{{{}}}", entries.join(","))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::VectorClock;
+
+    #[test]
+    fn clocks_order_events() {
+        let mut a = VectorClock::new();
+        a.tick(0);
+        let mut b = a.clone();
+        b.tick(1);
+        let mut c = VectorClock::new();
+        c.tick(2);
+        assert!(a.happened_before(&b));
+        assert!(!b.happened_before(&a));
+        assert!(!a.happened_before(&a));
+        assert!(b.concurrent(&c));
+
+        c.merge(&b);
+        c.tick(2);
+        assert_eq!((c.get(0), c.get(1), c.get(2), c.get(3)), (1, 1, 2, 0));
+        assert!(b.happened_before(&c));
+        assert_eq!(
+            c.to_json(|id| format!("n{id}")),
+            "{\"n0\":1,\"n1\":1,\"n2\":2}"
+        );
+    }
+}
@@ -0,0 +1,190 @@
+use rand::{rngs::StdRng, Rng};
+use std::{f64::consts::PI, fs, io, path::Path};
+
+/// How long a packet travels between two links. All models except
+/// `PerPacket` are drawn once per ordered pair of links when the network is
+/// created, `PerPacket` draws its inner model again for every packet.
+#[derive(Clone, Debug)]
+pub enum LatencyModel {
+    Constant(usize),
+    Uniform { max: usize }, // 0..max
+    Normal { mean: f64, std_dev: f64 },
+    Exponential { mean: f64 },
+    Pareto { scale: f64, shape: f64 },
+    Matrix(Vec<Vec<usize>>), // [sender][receiver]
+    PerPacket(Box<LatencyModel>),
+}
+
+impl LatencyModel {
+    /// Reads a matrix of latencies with one whitespace separated row per
+    /// sender, e.g. `0 3 5\n3 0 1\n5 1 0` for three links.
+    pub fn from_file<P: AsRef<Path>>(path: P, link_count: usize) -> io::Result<Self> {
+        let content = fs::read_to_string(path)?;
+        Self::parse_matrix(&content)
+            .and_then(|model| model.validate(link_count).map(|_| model))
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    }
+
+    pub fn parse_matrix(content: &str) -> Result<Self, String> {
+        let matrix = content
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .map(|line| {
+                line.split_whitespace()
+                    .map(|v| v.parse().map_err(|_| format!("Invalid latency {v}")))
+                    .collect::<Result<Vec<usize>, String>>()
+            })
+            .collect::<Result<Vec<Vec<usize>>, String>>()?;
+        if matrix.iter().any(|row| row.len() != matrix.len()) {
+            return Err(String::from("Latency matrix is not square"));
+        }
+        Ok(LatencyModel::Matrix(matrix))
+    }
+
+    /// Checks that a matrix has a row and column per link and that the
+    /// parameters of the distributions are valid.
+    pub fn validate(&self, link_count: usize) -> Result<(), String> {
+        match self {
+            Self::Matrix(matrix)
+                if matrix.len() != link_count || matrix.iter().any(|r| r.len() != link_count) =>
+            {
+                return Err(format!("Latency matrix is not {link_count}x{link_count}"));
+            }
+            Self::Normal { mean, std_dev } if !(mean.is_finite() && *std_dev >= 0.0) => {
+                return Err(format!("Invalid normal distribution {mean}, {std_dev}"));
+            }
+            Self::Exponential { mean } if !(*mean >= 0.0 && mean.is_finite()) => {
+                return Err(format!("Invalid exponential mean {mean}"));
+            }
+            Self::Pareto { scale, shape } if !(*scale > 0.0 && *shape > 0.0) => {
+                return Err(format!("Invalid pareto distribution {scale}, {shape}"));
+            }
+            Self::PerPacket(model) => return model.validate(link_count),
+            _ => {}
+        }
+        Ok(())
+    }
+
+    /// Latencies of all ordered pairs of links, `None` if they are drawn per
+    /// packet instead.
+    pub fn matrix(&self, link_count: usize, rng: &mut StdRng) -> Option<Vec<Vec<usize>>> {
+        if let Err(e) = self.validate(link_count) {
+            panic!("{e}");
+        }
+        match self {
+            Self::PerPacket(_) => None,
+            Self::Matrix(matrix) => Some(matrix.clone()),
+            _ => {
+                let mut latencies = vec![vec![0; link_count]; link_count];
+                for (i, vec) in latencies.iter_mut().enumerate() {
+                    for (j, val) in vec.iter_mut().enumerate() {
+                        if i == j {
+                            *val = 0
+                        } else {
+                            *val = self.sample(i, j, rng)
+                        }
+                    }
+                }
+                Some(latencies)
+            }
+        }
+    }
+
+    pub fn sample(&self, sender: usize, receiver: usize, rng: &mut StdRng) -> usize {
+        let latency = match self {
+            Self::Constant(latency) => return *latency,
+            Self::Uniform { max: 0 } => return 0,
+            Self::Uniform { max } => return rng.gen_range(0..*max),
+            Self::Matrix(matrix) => return matrix[sender][receiver],
+            Self::PerPacket(model) => return model.sample(sender, receiver, rng),
+            Self::Normal { mean, std_dev } => {
+                // Box-Muller transform, 1 - u keeps the logarithm finite.
+                let u1: f64 = 1.0 - rng.gen::<f64>();
+                let u2: f64 = rng.gen();
+                mean + std_dev * (-2.0 * u1.ln()).sqrt() * (2.0 * PI * u2).cos()
+            }
+            Self::Exponential { mean } => -mean * (1.0 - rng.gen::<f64>()).ln(),
+            Self::Pareto { scale, shape } => scale / (1.0 - rng.gen::<f64>()).powf(1.0 / shape),
+        };
+        // Casting saturates, so huge draws from heavy tails stay usable.
+        latency.max(0.0).round() as usize
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::LatencyModel;
+    use rand::{rngs::StdRng, SeedableRng};
+
+    fn mean(model: &LatencyModel, samples: usize) -> f64 {
+        let mut rng = StdRng::seed_from_u64(42);
+        let sum: usize = (0..samples).map(|_| model.sample(0, 1, &mut rng)).sum();
+        sum as f64 / samples as f64
+    }
+
+    #[test]
+    fn distributions_have_expected_mean() {
+        let normal = LatencyModel::Normal {
+            mean: 20.0,
+            std_dev: 5.0,
+        };
+        let exponential = LatencyModel::Exponential { mean: 10.0 };
+        let pareto = LatencyModel::Pareto {
+            scale: 4.0,
+            shape: 3.0,
+        };
+        assert!((mean(&normal, 10_000) - 20.0).abs() < 1.0);
+        assert!((mean(&exponential, 10_000) - 10.0).abs() < 1.0);
+        // E[X] = shape * scale / (shape - 1) = 6
+        assert!((mean(&pareto, 10_000) - 6.0).abs() < 1.0);
+        assert_eq!(mean(&LatencyModel::Constant(7), 100), 7.0);
+    }
+
+    #[test]
+    fn rejects_invalid_parameters() {
+        let pareto = LatencyModel::Pareto {
+            scale: 4.0,
+            shape: 0.0,
+        };
+        let normal = LatencyModel::Normal {
+            mean: 20.0,
+            std_dev: -1.0,
+        };
+        assert!(pareto.validate(3).is_err());
+        assert!(LatencyModel::PerPacket(Box::new(normal))
+            .validate(3)
+            .is_err());
+        assert!(LatencyModel::Matrix(vec![vec![0, 1], vec![1, 0]])
+            .validate(3)
+            .is_err());
+        assert!(LatencyModel::Exponential { mean: 10.0 }.validate(3).is_ok());
+    }
+
+    #[test]
+    fn per_packet_resamples() {
+        let mut rng = StdRng::seed_from_u64(42);
+        let model = LatencyModel::PerPacket(Box::new(LatencyModel::Uniform { max: 100 }));
+        assert!(model.matrix(3, &mut rng).is_none());
+        let samples: Vec<usize> = (0..10).map(|_| model.sample(0, 1, &mut rng)).collect();
+        assert!(samples.iter().any(|s| *s != samples[0]));
+    }
+
+    #[test]
+    fn matrix_from_file() {
+        let name = format!(
+            "latencies_{}_{:?}.txt",
+            std::process::id(),
+            std::thread::current().id()
+        );
+        let path = std::env::temp_dir().join(name);
+        std::fs::write(&path, "0 3 5\n3 0 1\n\n5 1 0\n").unwrap();
+        let model = LatencyModel::from_file(&path, 3).unwrap();
+        assert!(LatencyModel::from_file(&path, 4).is_err());
+        std::fs::remove_file(&path).unwrap();
+        let mut rng = StdRng::seed_from_u64(42);
+        let matrix = model.matrix(3, &mut rng).unwrap();
+        assert_eq!(matrix, vec![vec![0, 3, 5], vec![3, 0, 1], vec![5, 1, 0]]);
+        assert!(LatencyModel::parse_matrix("0 1\n1").is_err());
+        assert!(LatencyModel::parse_matrix("0 x\n1 0").is_err());
+    }
+}
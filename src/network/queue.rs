@@ -0,0 +1,121 @@
+use super::Packet;
+use std::{cmp::Ordering, collections::BinaryHeap};
+
+/// In-flight packets ordered by their delivery round. Packets due in the same
+/// round leave the queue in the order they were pushed.
+pub struct EventQueue<M> {
+    heap: BinaryHeap<Scheduled<M>>,
+    next_seq: usize,
+}
+
+struct Scheduled<M> {
+    time: usize,
+    seq: usize,
+    packet: Packet<M>,
+}
+
+impl<M> EventQueue<M> {
+    pub fn new() -> Self {
+        EventQueue {
+            heap: BinaryHeap::new(),
+            next_seq: 0,
+        }
+    }
+
+    pub fn push(&mut self, time: usize, packet: Packet<M>) {
+        self.heap.push(Scheduled {
+            time,
+            seq: self.next_seq,
+            packet,
+        });
+        self.next_seq += 1;
+    }
+
+    /// Removes the next packet due at or before `time`.
+    pub fn pop_due(&mut self, time: usize) -> Option<Packet<M>> {
+        if self.next_time()? <= time {
+            self.heap.pop().map(|s| s.packet)
+        } else {
+            None
+        }
+    }
+
+    pub fn next_time(&self) -> Option<usize> {
+        self.heap.peek().map(|s| s.time)
+    }
+
+    pub fn len(&self) -> usize {
+        self.heap.len()
+    }
+
+    /// Removes the first packet with `id` to leave the queue.
+    pub fn remove(&mut self, id: usize) -> Option<(usize, Packet<M>)> {
+        let mut scheduled = std::mem::take(&mut self.heap).into_vec();
+        let index = (0..scheduled.len())
+            .filter(|&i| scheduled[i].packet.id == id)
+            .min_by_key(|&i| (scheduled[i].time, scheduled[i].seq));
+        let removed = index.map(|i| scheduled.swap_remove(i));
+        self.heap = scheduled.into();
+        removed.map(|s| (s.time, s.packet))
+    }
+
+    /// Every packet with its time, in the order they leave the queue.
+    pub fn packets(&self) -> Vec<(usize, &Packet<M>)> {
+        let mut scheduled: Vec<&Scheduled<M>> = self.heap.iter().collect();
+        scheduled.sort_by_key(|s| (s.time, s.seq));
+        scheduled.into_iter().map(|s| (s.time, &s.packet)).collect()
+    }
+}
+
+// BinaryHeap is a max-heap, so the earliest (time, seq) has to compare largest.
+impl<M> Ord for Scheduled<M> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        (other.time, other.seq).cmp(&(self.time, self.seq))
+    }
+}
+
+impl<M> PartialOrd for Scheduled<M> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<M> PartialEq for Scheduled<M> {
+    fn eq(&self, other: &Self) -> bool {
+        (self.time, self.seq) == (other.time, other.seq)
+    }
+}
+
+impl<M> Eq for Scheduled<M> {}
+
+#[cfg(test)]
+mod test {
+    use super::EventQueue;
+    use crate::network::Packet;
+
+    fn packet(content: usize) -> Packet<usize> {
+        Packet {
+            id: content,
+            sender: 0,
+            receiver: 1,
+            content,
+            clock: Default::default(),
+        }
+    }
+
+    #[test]
+    fn pops_in_time_then_insertion_order() {
+        let mut queue = EventQueue::new();
+        queue.push(5, packet(1));
+        queue.push(2, packet(2));
+        queue.push(5, packet(3));
+        queue.push(2, packet(4));
+        assert_eq!(queue.pop_due(1).map(|p| p.content), None);
+        let mut order = Vec::new();
+        while let Some(p) = queue.pop_due(5) {
+            order.push(p.content);
+        }
+        assert_eq!(order, vec![2, 4, 1, 3]);
+        assert_eq!(queue.len(), 0);
+    }
+}
@@ -0,0 +1,205 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::str::FromStr;
+
+/// Every decision of a network that depends on its rng or its scheduler,
+/// by packet id, and the random choices of the nodes. Replaying it
+/// reproduces a run without drawing a single random number.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Recording {
+    pub dropped: BTreeSet<usize>,
+    pub duplicated: BTreeSet<usize>,
+    /// Ids of the packets the scheduler delivered per round, in delivery
+    /// order. Duplicated packets appear twice.
+    pub deliveries: BTreeMap<usize, Vec<usize>>,
+    /// Ids of the packets dropped by hand per round, e.g. in the stepper.
+    pub discarded: BTreeMap<usize, Vec<usize>>,
+    /// Inputs of the nodes by node id, in the order they were drawn.
+    pub inputs: Vec<(usize, String)>,
+    /// Rounds in which nodes crash by node id.
+    pub crashes: BTreeMap<usize, usize>,
+    /// Crashes by node and round, with the round the node recovers in.
+    pub faults: Vec<(usize, usize, Option<usize>)>,
+    /// Random bits every node drew while running, e.g. coin flips.
+    pub coins: BTreeMap<usize, Vec<bool>>,
+    /// The kind of nodes that ran, e.g. `multi-paxos 2`.
+    pub variant: Option<String>,
+    /// Whether idle rounds were skipped.
+    pub skip_idle: bool,
+}
+
+impl Recording {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The first input of `node`.
+    pub fn input<T: FromStr>(&self, node: usize) -> Result<T, String> {
+        let (_, input) = self
+            .inputs
+            .iter()
+            .find(|(id, _)| *id == node)
+            .ok_or(format!("No input of node {node} recorded"))?;
+        input
+            .parse()
+            .map_err(|_| format!("Invalid input {input} of node {node}"))
+    }
+
+    /// Every input of `node`, in the order they were drawn.
+    pub fn inputs_of<T: FromStr>(&self, node: usize) -> Result<Vec<T>, String> {
+        let inputs = self.inputs.iter().filter(|(id, _)| *id == node);
+        inputs
+            .map(|(_, input)| {
+                input
+                    .parse()
+                    .map_err(|_| format!("Invalid input {input} of node {node}"))
+            })
+            .collect()
+    }
+
+    /// One line per decision, e.g. `drop 3`, `duplicate 5` or
+    /// `deliver 4 0 1 2` for the packets 0, 1 and 2 delivered in round 4,
+    /// followed by `discard 4 3`, `input 1 true`, `crash 2 17`,
+    /// `fault 2 10 12`, `coins 2 1 0 0`, `variant multi-decree` and
+    /// `skip-idle`.
+    pub fn to_text(&self) -> String {
+        let mut text = String::new();
+        for id in &self.dropped {
+            text += &format!("drop {id}\n");
+        }
+        for id in &self.duplicated {
+            text += &format!("duplicate {id}\n");
+        }
+        for (round, ids) in &self.deliveries {
+            let ids: Vec<String> = ids.iter().map(|id| id.to_string()).collect();
+            text += &format!("deliver {round} {}\n", ids.join(" "));
+        }
+        for (round, ids) in &self.discarded {
+            let ids: Vec<String> = ids.iter().map(|id| id.to_string()).collect();
+            text += &format!("discard {round} {}\n", ids.join(" "));
+        }
+        for (node, input) in &self.inputs {
+            text += &format!("input {node} {input}\n");
+        }
+        for (node, round) in &self.crashes {
+            text += &format!("crash {node} {round}\n");
+        }
+        for (node, round, recover) in &self.faults {
+            match recover {
+                Some(recover) => text += &format!("fault {node} {round} {recover}\n"),
+                None => text += &format!("fault {node} {round}\n"),
+            }
+        }
+        for (node, flips) in &self.coins {
+            let flips: Vec<String> = flips.iter().map(|f| (*f as u8).to_string()).collect();
+            text += &format!("coins {node} {}\n", flips.join(" "));
+        }
+        if let Some(variant) = &self.variant {
+            text += &format!("variant {variant}\n");
+        }
+        if self.skip_idle {
+            text += "skip-idle\n";
+        }
+        text
+    }
+
+    pub fn parse(content: &str) -> Result<Self, String> {
+        let mut recording = Recording::new();
+        for line in content.lines().filter(|line| !line.trim().is_empty()) {
+            let mut words = line.split_whitespace();
+            let keyword = words.next().unwrap_or_default();
+            if keyword == "input" {
+                let node = words.next().ok_or(format!("Invalid line {line}"))?;
+                let node = node.parse().map_err(|_| format!("Invalid number {node}"))?;
+                let input: Vec<&str> = words.collect();
+                recording.inputs.push((node, input.join(" ")));
+                continue;
+            }
+            if keyword == "variant" {
+                let variant: Vec<&str> = words.collect();
+                recording.variant = Some(variant.join(" "));
+                continue;
+            }
+            let numbers = words
+                .map(|w| w.parse().map_err(|_| format!("Invalid number {w}")))
+                .collect::<Result<Vec<usize>, String>>()?;
+            match (keyword, numbers.as_slice()) {
+                ("drop", [id]) => {
+                    recording.dropped.insert(*id);
+                }
+                ("duplicate", [id]) => {
+                    recording.duplicated.insert(*id);
+                }
+                ("deliver", [round, ids @ ..]) => {
+                    recording.deliveries.insert(*round, ids.to_vec());
+                }
+                ("discard", [round, ids @ ..]) if !ids.is_empty() => {
+                    recording.discarded.insert(*round, ids.to_vec());
+                }
+                ("crash", [node, round]) => {
+                    recording.crashes.insert(*node, *round);
+                }
+                ("fault", [node, round]) => recording.faults.push((*node, *round, None)),
+                ("fault", [node, round, recover]) => {
+                    recording.faults.push((*node, *round, Some(*recover)))
+                }
+                ("skip-idle", []) => recording.skip_idle = true,
+                ("coins", [node, flips @ ..]) if flips.iter().all(|f| *f <= 1) => {
+                    let flips = flips.iter().map(|f| *f == 1).collect();
+                    recording.coins.insert(*node, flips);
+                }
+                _ => return Err(format!("Invalid line {line}")),
+            }
+        }
+        Ok(recording)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::Recording;
+
+    #[test]
+    fn recording_survives_text() {
+        let mut recording = Recording::new();
+        recording.dropped.insert(3);
+        recording.duplicated.insert(5);
+        recording.deliveries.insert(4, vec![0, 5, 1, 5]);
+        let text = recording.to_text();
+        assert_eq!(text, "drop 3\nduplicate 5\ndeliver 4 0 5 1 5\n");
+        assert_eq!(Recording::parse(&text), Ok(recording.clone()));
+
+        recording.discarded.insert(6, vec![2, 8]);
+        recording.inputs = vec![(1, String::from("true")), (2, String::from("set x 1"))];
+        recording.crashes.insert(2, 17);
+        recording.coins.insert(0, vec![true, false]);
+        let text = recording.to_text();
+        assert!(text
+            .ends_with("discard 6 2 8\ninput 1 true\ninput 2 set x 1\ncrash 2 17\ncoins 0 1 0\n"));
+        let parsed = Recording::parse(&text).unwrap();
+        assert_eq!(parsed, recording);
+        assert_eq!(parsed.input::<bool>(1), Ok(true));
+        assert!(parsed.input::<bool>(2).is_err());
+        assert!(parsed.input::<bool>(3).is_err());
+
+        recording.inputs.push((1, String::from("false")));
+        recording.faults = vec![(2, 10, Some(12)), (2, 20, None)];
+        recording.variant = Some(String::from("multi-paxos 2"));
+        recording.skip_idle = true;
+        let text = recording.to_text();
+        assert!(text.ends_with(
+            "fault 2 10 12\nfault 2 20\ncoins 0 1 0\nvariant multi-paxos 2\nskip-idle\n"
+        ));
+        let parsed = Recording::parse(&text).unwrap();
+        assert_eq!(parsed, recording);
+        assert_eq!(parsed.inputs_of::<bool>(1), Ok(vec![true, false]));
+        assert_eq!(parsed.inputs_of::<bool>(3), Ok(vec![]));
+        assert!(parsed.inputs_of::<bool>(2).is_err());
+        assert!(Recording::parse("fault 1").is_err());
+        assert!(Recording::parse("skip-idle 1").is_err());
+        assert!(Recording::parse("coins 0 2").is_err());
+        assert!(Recording::parse("deliver").is_err());
+        assert!(Recording::parse("discard 3").is_err());
+        assert!(Recording::parse("drop x").is_err());
+        assert!(Recording::parse("swap 1 2").is_err());
+    }
+}
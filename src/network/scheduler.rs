@@ -0,0 +1,531 @@
+use super::{queue::EventQueue, Packet};
+use rand::{rngs::StdRng, Rng};
+use std::collections::{BTreeMap, HashMap};
+
+/// Decides when the packets in flight reach their receiver. The network
+/// hands every sent packet to the scheduler together with the round the
+/// latency model proposes and asks it every round which packets to deliver.
+pub trait Scheduler<M> {
+    /// Takes over a sent packet the latency model would deliver in `due`.
+    fn push(&mut self, due: usize, packet: Packet<M>);
+    /// Packets to deliver in `round`, in delivery order.
+    fn deliver(&mut self, round: usize, rng: &mut StdRng) -> Vec<Packet<M>>;
+    /// Earliest round from `round` on in which `deliver` may return packets.
+    fn next_delivery(&self, round: usize) -> Option<usize>;
+    /// Number of packets the scheduler still holds.
+    fn len(&self) -> usize;
+    /// Every packet the scheduler holds with the round it will deliver it
+    /// in, `None` if that is not known yet.
+    fn pending(&self) -> Vec<(Option<usize>, &Packet<M>)>;
+    /// Takes back the next packet with `id` that would be delivered.
+    fn remove(&mut self, id: usize) -> Option<Packet<M>>;
+    /// Delivers the next packet with `id` `rounds` rounds after it was due,
+    /// but not before `round`. Returns false if the scheduler does not go by
+    /// due rounds or holds no such packet.
+    fn delay(&mut self, _id: usize, _rounds: usize, _round: usize) -> bool {
+        false
+    }
+
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// Delivers every packet in the round proposed by the latency model.
+pub struct LatencyOrder<M> {
+    queue: EventQueue<M>,
+}
+
+/// Like `LatencyOrder`, but a packet never overtakes an earlier one sent
+/// from the same sender to the same receiver.
+pub struct Fifo<M> {
+    queue: EventQueue<M>,
+    last_due: HashMap<(usize, usize), usize>,
+}
+
+/// Ignores latencies and delivers `per_round` random packets each round.
+pub struct RandomOrder<M> {
+    packets: Vec<Packet<M>>,
+    per_round: usize,
+}
+
+/// Delivers packets in the rounds and order of a `Recording`, whatever
+/// their latency. Recorded packets that were never sent are skipped, so a
+/// diverging replay shows up in the trace.
+pub struct Replay<M> {
+    deliveries: BTreeMap<usize, Vec<usize>>,
+    packets: HashMap<usize, Vec<Packet<M>>>, // by id, duplicates share one
+    len: usize,
+}
+
+/// Withholds every packet addressed to `node` until round `until`, or
+/// forever without one. Everything else is left to `inner`.
+pub struct Starve<M> {
+    node: usize,
+    until: Option<usize>,
+    inner: Box<dyn Scheduler<M>>,
+    withheld: Vec<Packet<M>>,
+}
+
+impl<M> LatencyOrder<M> {
+    pub fn new() -> Self {
+        LatencyOrder {
+            queue: EventQueue::new(),
+        }
+    }
+}
+
+impl<M> Default for LatencyOrder<M> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<M> Scheduler<M> for LatencyOrder<M> {
+    fn push(&mut self, due: usize, packet: Packet<M>) {
+        self.queue.push(due, packet)
+    }
+
+    fn deliver(&mut self, round: usize, _rng: &mut StdRng) -> Vec<Packet<M>> {
+        let mut packets = Vec::new();
+        while let Some(packet) = self.queue.pop_due(round) {
+            packets.push(packet)
+        }
+        packets
+    }
+
+    fn next_delivery(&self, round: usize) -> Option<usize> {
+        self.queue.next_time().map(|time| time.max(round))
+    }
+
+    fn len(&self) -> usize {
+        self.queue.len()
+    }
+
+    fn pending(&self) -> Vec<(Option<usize>, &Packet<M>)> {
+        let packets = self.queue.packets().into_iter();
+        packets.map(|(due, packet)| (Some(due), packet)).collect()
+    }
+
+    fn remove(&mut self, id: usize) -> Option<Packet<M>> {
+        self.queue.remove(id).map(|(_, packet)| packet)
+    }
+
+    fn delay(&mut self, id: usize, rounds: usize, round: usize) -> bool {
+        let Some((due, packet)) = self.queue.remove(id) else {
+            return false;
+        };
+        self.queue
+            .push(due.max(round).saturating_add(rounds), packet);
+        true
+    }
+}
+
+impl<M> Fifo<M> {
+    pub fn new() -> Self {
+        Fifo {
+            queue: EventQueue::new(),
+            last_due: HashMap::new(),
+        }
+    }
+}
+
+impl<M> Default for Fifo<M> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<M> Scheduler<M> for Fifo<M> {
+    fn push(&mut self, due: usize, packet: Packet<M>) {
+        let last = self
+            .last_due
+            .entry((packet.sender, packet.receiver))
+            .or_insert(0);
+        *last = due.max(*last);
+        self.queue.push(*last, packet)
+    }
+
+    fn deliver(&mut self, round: usize, _rng: &mut StdRng) -> Vec<Packet<M>> {
+        let mut packets = Vec::new();
+        while let Some(packet) = self.queue.pop_due(round) {
+            packets.push(packet)
+        }
+        packets
+    }
+
+    fn next_delivery(&self, round: usize) -> Option<usize> {
+        self.queue.next_time().map(|time| time.max(round))
+    }
+
+    fn len(&self) -> usize {
+        self.queue.len()
+    }
+
+    fn pending(&self) -> Vec<(Option<usize>, &Packet<M>)> {
+        let packets = self.queue.packets().into_iter();
+        packets.map(|(due, packet)| (Some(due), packet)).collect()
+    }
+
+    fn remove(&mut self, id: usize) -> Option<Packet<M>> {
+        self.queue.remove(id).map(|(_, packet)| packet)
+    }
+
+    /// Packets sent after the delayed one on the same channel are delayed
+    /// along with it, so none of them overtakes it.
+    fn delay(&mut self, id: usize, rounds: usize, round: usize) -> bool {
+        let Some((due, packet)) = self.queue.remove(id) else {
+            return false;
+        };
+        let channel = (packet.sender, packet.receiver);
+        let delayed = due.max(round).saturating_add(rounds);
+        let mut packets = vec![(delayed, packet)];
+        let later: Vec<usize> = self
+            .queue
+            .packets()
+            .into_iter()
+            .filter(|(_, p)| (p.sender, p.receiver) == channel && p.id > id)
+            .map(|(_, p)| p.id)
+            .collect();
+        for id in later {
+            let (due, packet) = self
+                .queue
+                .remove(id)
+                .expect("Queued packets can be removed");
+            packets.push((due.max(delayed), packet));
+        }
+        // Pushing in id order keeps the channel order within a round.
+        packets.sort_by_key(|(_, packet)| packet.id);
+        for (due, packet) in packets {
+            self.queue.push(due, packet);
+        }
+        let last = self.last_due.entry(channel).or_insert(0);
+        *last = delayed.max(*last);
+        true
+    }
+}
+
+impl<M> RandomOrder<M> {
+    pub fn new(per_round: usize) -> Self {
+        assert!(per_round > 0, "RandomOrder has to deliver something");
+        RandomOrder {
+            packets: Vec::new(),
+            per_round,
+        }
+    }
+}
+
+impl<M> Scheduler<M> for RandomOrder<M> {
+    fn push(&mut self, _due: usize, packet: Packet<M>) {
+        self.packets.push(packet)
+    }
+
+    fn deliver(&mut self, _round: usize, rng: &mut StdRng) -> Vec<Packet<M>> {
+        let count = self.per_round.min(self.packets.len());
+        (0..count)
+            .map(|_| {
+                let index = rng.gen_range(0..self.packets.len());
+                self.packets.swap_remove(index)
+            })
+            .collect()
+    }
+
+    fn next_delivery(&self, round: usize) -> Option<usize> {
+        (!self.packets.is_empty()).then_some(round)
+    }
+
+    fn len(&self) -> usize {
+        self.packets.len()
+    }
+
+    fn pending(&self) -> Vec<(Option<usize>, &Packet<M>)> {
+        self.packets.iter().map(|packet| (None, packet)).collect()
+    }
+
+    fn remove(&mut self, id: usize) -> Option<Packet<M>> {
+        let index = self.packets.iter().position(|packet| packet.id == id)?;
+        Some(self.packets.swap_remove(index))
+    }
+}
+
+impl<M> Replay<M> {
+    pub fn new(deliveries: BTreeMap<usize, Vec<usize>>) -> Self {
+        Replay {
+            deliveries,
+            packets: HashMap::new(),
+            len: 0,
+        }
+    }
+}
+
+impl<M> Scheduler<M> for Replay<M> {
+    fn push(&mut self, _due: usize, packet: Packet<M>) {
+        self.packets.entry(packet.id).or_default().push(packet);
+        self.len += 1;
+    }
+
+    fn deliver(&mut self, round: usize, _rng: &mut StdRng) -> Vec<Packet<M>> {
+        let mut packets = Vec::new();
+        for id in self.deliveries.remove(&round).unwrap_or_default() {
+            let Some(copies) = self.packets.get_mut(&id) else {
+                continue;
+            };
+            packets.push(copies.remove(0));
+            if copies.is_empty() {
+                self.packets.remove(&id);
+            }
+            self.len -= 1;
+        }
+        packets
+    }
+
+    fn next_delivery(&self, round: usize) -> Option<usize> {
+        if self.len == 0 {
+            return None;
+        }
+        self.deliveries
+            .range(round..)
+            .next()
+            .map(|(&round, _)| round)
+    }
+
+    fn len(&self) -> usize {
+        self.len
+    }
+
+    fn pending(&self) -> Vec<(Option<usize>, &Packet<M>)> {
+        let mut pending = Vec::new();
+        let mut scheduled: HashMap<usize, usize> = HashMap::new();
+        for (&round, ids) in &self.deliveries {
+            for id in ids {
+                let Some(copies) = self.packets.get(id) else {
+                    continue;
+                };
+                let index = scheduled.entry(*id).or_insert(0);
+                if let Some(packet) = copies.get(*index) {
+                    pending.push((Some(round), packet));
+                    *index += 1;
+                }
+            }
+        }
+        for (id, copies) in &self.packets {
+            let index = scheduled.get(id).copied().unwrap_or(0);
+            pending.extend(copies[index..].iter().map(|packet| (None, packet)));
+        }
+        pending
+    }
+
+    fn remove(&mut self, id: usize) -> Option<Packet<M>> {
+        let copies = self.packets.get_mut(&id)?;
+        let packet = copies.remove(0);
+        if copies.is_empty() {
+            self.packets.remove(&id);
+        }
+        self.len -= 1;
+        Some(packet)
+    }
+}
+
+impl<M> Starve<M> {
+    pub fn new(node: usize, until: Option<usize>, inner: Box<dyn Scheduler<M>>) -> Self {
+        Starve {
+            node,
+            until,
+            inner,
+            withheld: Vec::new(),
+        }
+    }
+
+    fn starving(&self, round: usize) -> bool {
+        self.until.is_none_or(|until| round < until)
+    }
+}
+
+impl<M> Scheduler<M> for Starve<M> {
+    fn push(&mut self, due: usize, packet: Packet<M>) {
+        self.inner.push(due, packet)
+    }
+
+    fn deliver(&mut self, round: usize, rng: &mut StdRng) -> Vec<Packet<M>> {
+        let (starved, mut packets): (Vec<Packet<M>>, Vec<Packet<M>>) = self
+            .inner
+            .deliver(round, rng)
+            .into_iter()
+            .partition(|p| p.receiver == self.node && self.starving(round));
+        self.withheld.extend(starved);
+        if !self.starving(round) {
+            packets.splice(0..0, self.withheld.drain(..));
+        }
+        packets
+    }
+
+    fn next_delivery(&self, round: usize) -> Option<usize> {
+        let release = match self.until {
+            Some(until) if !self.withheld.is_empty() => Some(until.max(round)),
+            _ => None,
+        };
+        self.inner
+            .next_delivery(round)
+            .into_iter()
+            .chain(release)
+            .min()
+    }
+
+    fn len(&self) -> usize {
+        self.inner.len() + self.withheld.len()
+    }
+
+    fn pending(&self) -> Vec<(Option<usize>, &Packet<M>)> {
+        let release = self.until;
+        let withheld = self.withheld.iter().map(|packet| (release, packet));
+        let inner = self
+            .inner
+            .pending()
+            .into_iter()
+            .map(|(due, packet)| match due {
+                Some(due) if packet.receiver == self.node && self.starving(due) => {
+                    (release, packet)
+                }
+                _ => (due, packet),
+            });
+        withheld.chain(inner).collect()
+    }
+
+    fn remove(&mut self, id: usize) -> Option<Packet<M>> {
+        match self.withheld.iter().position(|packet| packet.id == id) {
+            Some(index) => Some(self.withheld.remove(index)),
+            None => self.inner.remove(id),
+        }
+    }
+
+    /// Withheld packets wait for the release anyway.
+    fn delay(&mut self, id: usize, rounds: usize, round: usize) -> bool {
+        !self.withheld.iter().any(|packet| packet.id == id) && self.inner.delay(id, rounds, round)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use rand::SeedableRng;
+
+    fn packet(sender: usize, receiver: usize, content: usize) -> Packet<usize> {
+        Packet {
+            id: content,
+            sender,
+            receiver,
+            content,
+            clock: Default::default(),
+        }
+    }
+
+    fn contents(packets: Vec<Packet<usize>>) -> Vec<usize> {
+        packets.into_iter().map(|p| p.content).collect()
+    }
+
+    #[test]
+    fn fifo_keeps_channel_order() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let mut fifo = Fifo::new();
+        fifo.push(5, packet(0, 1, 1));
+        fifo.push(2, packet(0, 1, 2));
+        fifo.push(2, packet(2, 1, 3));
+        assert_eq!(contents(fifo.deliver(2, &mut rng)), vec![3]);
+        assert_eq!(fifo.next_delivery(3), Some(5));
+        assert_eq!(contents(fifo.deliver(5, &mut rng)), vec![1, 2]);
+    }
+
+    #[test]
+    fn fifo_delays_later_packets_of_the_channel() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let mut fifo = Fifo::new();
+        fifo.push(1, packet(0, 1, 1));
+        fifo.push(2, packet(0, 1, 2));
+        fifo.push(2, packet(2, 1, 3));
+        assert!(fifo.delay(1, 3, 0));
+        assert!(!fifo.delay(7, 3, 0));
+        assert_eq!(contents(fifo.deliver(2, &mut rng)), vec![3]);
+        assert_eq!(contents(fifo.deliver(4, &mut rng)), vec![1, 2]);
+        fifo.push(0, packet(0, 1, 4));
+        assert_eq!(fifo.next_delivery(0), Some(4));
+    }
+
+    #[test]
+    fn only_schedulers_with_due_rounds_delay() {
+        let mut latency = LatencyOrder::new();
+        latency.push(2, packet(0, 1, 1));
+        assert!(latency.delay(1, 3, 4));
+        assert_eq!(latency.next_delivery(0), Some(7));
+
+        let mut random = RandomOrder::new(1);
+        random.push(2, packet(0, 1, 1));
+        assert!(!random.delay(1, 3, 0));
+        let mut replay = Replay::new(BTreeMap::from([(2, vec![1])]));
+        replay.push(2, packet(0, 1, 1));
+        assert!(!replay.delay(1, 3, 0));
+    }
+
+    #[test]
+    fn random_order_delivers_everything() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let mut random = RandomOrder::new(2);
+        for i in 0..5 {
+            random.push(0, packet(0, 1, i));
+        }
+        let mut delivered = Vec::new();
+        for round in 0..3 {
+            delivered.extend(contents(random.deliver(round, &mut rng)));
+        }
+        assert_ne!(delivered, vec![0, 1, 2, 3, 4]);
+        delivered.sort();
+        assert_eq!(delivered, vec![0, 1, 2, 3, 4]);
+        assert!(random.is_empty());
+    }
+
+    #[test]
+    fn starve_withholds_until_release() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let mut starve = Starve::new(1, Some(3), Box::new(LatencyOrder::new()));
+        starve.push(0, packet(0, 1, 1));
+        starve.push(0, packet(1, 0, 2));
+        assert_eq!(contents(starve.deliver(0, &mut rng)), vec![2]);
+        assert_eq!(starve.next_delivery(1), Some(3));
+        assert!(starve.deliver(2, &mut rng).is_empty());
+        assert_eq!(contents(starve.deliver(3, &mut rng)), vec![1]);
+    }
+
+    #[test]
+    fn starve_reports_withheld_packets_as_pending() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let mut starve = Starve::new(1, Some(3), Box::new(LatencyOrder::new()));
+        starve.push(0, packet(0, 1, 1));
+        starve.deliver(0, &mut rng);
+        starve.push(2, packet(0, 1, 3));
+        starve.push(1, packet(1, 0, 4));
+        let pending: Vec<(Option<usize>, usize)> = starve
+            .pending()
+            .into_iter()
+            .map(|(due, p)| (due, p.content))
+            .collect();
+        assert_eq!(pending, vec![(Some(3), 1), (Some(1), 4), (Some(3), 3)]);
+        assert_eq!(contents(starve.deliver(2, &mut rng)), vec![4]);
+        assert_eq!(contents(starve.deliver(3, &mut rng)), vec![1, 3]);
+    }
+
+    #[test]
+    fn replay_follows_recorded_rounds() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let deliveries = BTreeMap::from([(2, vec![1, 0, 7]), (4, vec![1])]);
+        let mut replay = Replay::new(deliveries);
+        replay.push(9, packet(0, 1, 0));
+        replay.push(0, packet(0, 1, 1));
+        replay.push(0, packet(0, 1, 1));
+        assert_eq!(replay.next_delivery(0), Some(2));
+        assert!(replay.deliver(1, &mut rng).is_empty());
+        assert_eq!(contents(replay.deliver(2, &mut rng)), vec![1, 0]);
+        assert_eq!(replay.len(), 1);
+        assert_eq!(replay.next_delivery(3), Some(4));
+        assert_eq!(contents(replay.deliver(4, &mut rng)), vec![1]);
+        assert!(replay.is_empty());
+    }
+}
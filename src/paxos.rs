@@ -1,93 +1,750 @@
+pub mod checker;
 pub mod client;
+pub mod proposer;
+pub mod requester;
 pub mod server;
 
-use super::Logger;
-use crate::network::{Link, Network, Packet};
+use super::{Logger, NodeState, SimulationReport, Termination};
+use crate::network::{Kind, Link, Network, NetworkConfig, Packet, Recording, Scheduler};
+use crate::trace::{Actor, Field, TraceEvent, Traceable};
+use checker::{SafetyChecker, SafetyViolation};
 use client::Client;
+use proposer::Proposer;
+use rand::distributions::{Distribution, Standard};
 use rand::SeedableRng;
 use rand::{self, rngs::StdRng, Rng};
+use requester::Requester;
 use server::Server;
-use std::{cell::RefCell, fmt::Debug, rc::Rc, usize::MAX};
+use std::fmt::{Debug, Display};
+use std::str::FromStr;
+use std::{cell::RefCell, io, rc::Rc};
 
 const WAIT_DURATION: usize = 50;
 const SERVER: usize = 0;
 const CLIENT: usize = 1;
-type Ticket = usize;
+const PROPOSER: usize = 2;
+type Slot = usize;
 type ServerList = Rc<RefCell<Vec<usize>>>;
-type LinkInterface = Rc<RefCell<Link<Message>>>;
+type LinkInterface<V> = Rc<RefCell<Link<Message<V>>>>;
 
-#[derive(Debug, Clone, Copy)]
-pub enum Message {
-    Ask(Ticket),
-    Ok(Ticket, Command),
-    Propose(Ticket, Command),
-    Success,
-    Execute(Command),
+/// Anything the nodes can agree on, e.g. strings or key-value operations.
+pub trait Value: Clone + Debug + PartialEq + 'static {}
+
+impl<V: Clone + Debug + PartialEq + 'static> Value for V {}
+
+#[derive(Debug, Clone)]
+pub enum Message<V = bool> {
+    Ask(Slot, Ticket),
+    Ok(Slot, Ticket, Command<V>),
+    Propose(Slot, Ticket, Command<V>),
+    Success(Slot, Ticket),
+    Execute(Slot, Command<V>),
+    /// Multi-Paxos: a client's command with its sequence number.
+    Request(usize, Command<V>),
+    /// Multi-Paxos: the slot a client's request was decided in.
+    Reply(usize, Slot),
+    /// Multi-Paxos: asks for a ticket in all slots at once.
+    Prepare(Ticket),
+    /// Multi-Paxos: every slot with `(slot, t_store, command, decided)`.
+    Promise(Ticket, Vec<(Slot, Ticket, Command<V>, bool)>),
+}
+
+impl<V> Kind for Message<V> {
+    fn kind(&self) -> &'static str {
+        match self {
+            Self::Ask(..) => "Ask",
+            Self::Ok(..) => "Ok",
+            Self::Propose(..) => "Propose",
+            Self::Success(..) => "Success",
+            Self::Execute(..) => "Execute",
+            Self::Request(..) => "Request",
+            Self::Reply(..) => "Reply",
+            Self::Prepare(..) => "Prepare",
+            Self::Promise(..) => "Promise",
+        }
+    }
 }
 
-#[derive(Clone, Copy, PartialEq)]
-pub enum Command {
-    Defined(bool),
+#[derive(Clone, PartialEq)]
+pub enum Command<V = bool> {
+    Defined(CommandId, V),
     Undefined,
 }
 
-impl Debug for Command {
+/// The client that issued a command and its position among the client's
+/// commands, so equal values of different requests are kept apart.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct CommandId {
+    pub client: usize,
+    pub seq: usize,
+}
+
+impl<V> Command<V> {
+    pub fn new(client: usize, seq: usize, value: V) -> Self {
+        Self::Defined(CommandId { client, seq }, value)
+    }
+
+    pub fn id(&self) -> Option<CommandId> {
+        match self {
+            Self::Defined(id, _) => Some(*id),
+            Self::Undefined => None,
+        }
+    }
+
+    pub fn value(&self) -> Option<&V> {
+        match self {
+            Self::Defined(_, value) => Some(value),
+            Self::Undefined => None,
+        }
+    }
+}
+
+impl<V: Debug> Debug for Command<V> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
-            Self::Defined(true) => write!(f, "1"),
-            Self::Defined(false) => write!(f, "0"),
+            Self::Defined(_, value) => write!(f, "{value:?}"),
             Self::Undefined => write!(f, "⊥"),
         }
     }
 }
 
-trait Node {
+/// Ballot number of a proposal. Rounds are counted per node, so ties are
+/// broken by the id of the proposing node.
+#[derive(Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Ticket {
+    pub round: usize,
+    pub node: usize,
+}
+
+impl Ticket {
+    pub fn new(round: usize, node: usize) -> Self {
+        Ticket { round, node }
+    }
+}
+
+impl Debug for Ticket {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "({}, {})", self.round, self.node)
+    }
+}
+
+impl std::fmt::Display for Ticket {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{self:?}")
+    }
+}
+
+/// The kind of clients a system runs, recorded so that replays rebuild them.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Variant {
+    SingleDecree,
+    MultiDecree,
+    /// Multi-Paxos with this many proposers.
+    MultiPaxos(usize),
+}
+
+impl Display for Variant {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::SingleDecree => write!(f, "single-decree"),
+            Self::MultiDecree => write!(f, "multi-decree"),
+            Self::MultiPaxos(proposers) => write!(f, "multi-paxos {proposers}"),
+        }
+    }
+}
+
+impl FromStr for Variant {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let words: Vec<&str> = s.split_whitespace().collect();
+        match words.as_slice() {
+            ["single-decree"] => Ok(Self::SingleDecree),
+            ["multi-decree"] => Ok(Self::MultiDecree),
+            ["multi-paxos", proposers] => match proposers.parse() {
+                Ok(proposers) if proposers > 0 => Ok(Self::MultiPaxos(proposers)),
+                _ => Err(format!("Invalid proposer count {proposers}")),
+            },
+            _ => Err(format!("Invalid variant {s}")),
+        }
+    }
+}
+
+/// Crashes `node` at the start of `round`. With `recover` set, the node
+/// restarts at that round, otherwise it stays crashed for the rest of the run.
+#[derive(Clone, Copy, Debug)]
+pub struct Crash {
+    pub node: usize,
+    pub round: usize,
+    pub recover: Option<usize>,
+}
+
+trait Node<V>: Actor {
     fn exec(&mut self, logger: &mut Logger);
-    fn get_command(&self) -> Command;
+    fn get_command(&self) -> Command<V>;
     fn has_decided(&self) -> bool;
+    /// The decided prefix of the log as known by the node.
+    fn committed(&self) -> Vec<Command<V>>;
+    /// Every slot the node decided together with its value.
+    fn decisions(&self) -> Vec<(Slot, Command<V>)>;
+    /// Values the node may propose, asked for before the run starts.
+    fn inputs(&self) -> Vec<Command<V>>;
     fn get_type(&self) -> usize;
+    /// Loses all state that was not written to stable storage.
+    fn crash(&mut self);
+    /// Restarts the node from its stable storage.
+    fn recover(&mut self);
+    /// Number of rounds the node does nothing unless it receives a message.
+    fn idle_rounds(&self) -> usize;
+    /// Fast forwards the node by `rounds` idle rounds.
+    fn skip_rounds(&mut self, rounds: usize);
+    /// Current values of the node's variables.
+    fn state(&self) -> Vec<(String, String)>;
 }
-enum Action {
+enum Action<V> {
     Store(String, String),
-    Send(usize, Message),
+    Send(usize, usize, Message<V>), // receiver, packet id, message
     StateChange(usize, usize),
-    Receive(usize, Message),
+    Receive(Packet<Message<V>>),
     Check(String, String, bool),
-    Decide(Command),
+    Decide(Slot, Command<V>),
+    Crash,
+    Recover,
 }
 
-impl Debug for Action {
+impl<V: Debug> Debug for Action<V> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             Self::Store(var, value) => write!(f, "store {var} = {value}"),
-            Self::Send(receiver_id, message) => write!(f, "send {message:?} to {receiver_id}"),
+            Self::Send(receiver_id, _, message) => write!(f, "send {message:?} to {receiver_id}"),
             Self::StateChange(from, to) => write!(f, "change state from {from} to {to}"),
-            Self::Receive(sender_id, message) => {
-                write!(f, "received {message:?} from {sender_id}")
+            Self::Receive(packet) => {
+                write!(f, "received {:?} from {}", packet.content, packet.sender)
             }
             Self::Check(condition, values, result) => {
                 write!(f, "check {condition}: {values} => {result}")
             }
-            Self::Decide(command) => write!(f, "decides for {command:?}"),
+            Self::Decide(slot, command) => write!(f, "decides for {command:?} in slot {slot}"),
+            Self::Crash => write!(f, "crashes"),
+            Self::Recover => write!(f, "recovers"),
         }
     }
 }
 
-pub struct System {
-    nodes: Vec<Box<dyn Node>>,
-    network: Network<Message>,
+impl<V: Debug> Traceable for Action<V> {
+    fn action(&self) -> &'static str {
+        match self {
+            Self::Store(..) => "store",
+            Self::Send(..) => "send",
+            Self::StateChange(..) => "state_change",
+            Self::Receive(..) => "receive",
+            Self::Check(..) => "check",
+            Self::Decide(..) => "decide",
+            Self::Crash => "crash",
+            Self::Recover => "recover",
+        }
+    }
+
+    fn fields(&self) -> Vec<(&'static str, Field)> {
+        match self {
+            Self::Store(var, value) => vec![
+                ("variable", var.as_str().into()),
+                ("value", value.as_str().into()),
+            ],
+            Self::Send(receiver, id, message) => vec![
+                ("receiver", (*receiver).into()),
+                ("packet", (*id).into()),
+                ("message", Field::debug(message)),
+            ],
+            Self::Receive(packet) => vec![
+                ("sender", packet.sender.into()),
+                ("packet", packet.id.into()),
+                ("clock", Field::Clock(packet.clock.clone())),
+                ("message", Field::debug(&packet.content)),
+            ],
+            Self::Check(condition, values, result) => vec![
+                ("condition", condition.as_str().into()),
+                ("values", values.as_str().into()),
+                ("result", (*result).into()),
+            ],
+            Self::StateChange(from, to) => vec![("from", (*from).into()), ("to", (*to).into())],
+            Self::Decide(slot, command) => {
+                vec![("slot", (*slot).into()), ("command", Field::debug(command))]
+            }
+            Self::Crash | Self::Recover => Vec::new(),
+        }
+    }
+}
+
+pub struct System<V = bool> {
+    nodes: Vec<Box<dyn Node<V>>>,
+    network: Network<Message<V>>,
+    faults: Vec<Crash>,
+    crashed: Vec<Option<Crash>>, // the fault a node is down for
+    skip_idle: bool,
+    variant: Variant,
+    inputs: Vec<Command<V>>,
+    checker: SafetyChecker<V>,
+    violation: Option<SafetyViolation<V>>,
+    decision_rounds: Vec<Option<usize>>,
 }
 
-impl crate::System for System {
+impl<V: Value + Display + FromStr> crate::System for System<V>
+where
+    Standard: Distribution<V>,
+{
     fn new_rand(node_count: usize, server_count: usize, seed: Option<u64>) -> Self {
+        let config = NetworkConfig::new(true, 10);
+        Self::new_with_network(node_count, server_count, seed, config)
+    }
+
+    fn new_with_network(
+        node_count: usize,
+        server_count: usize,
+        seed: Option<u64>,
+        config: NetworkConfig,
+    ) -> Self {
+        Self::new_rand_from(node_count, server_count, seed, config, |rng| rng.gen())
+    }
+
+    fn from_recording(
+        node_count: usize,
+        server_count: usize,
+        config: NetworkConfig,
+        recording: Recording,
+    ) -> Result<Self, String> {
+        let variant = match &recording.variant {
+            Some(variant) => variant.parse()?,
+            None => Variant::SingleDecree,
+        };
+        let inputs = (server_count..node_count)
+            .map(|id| match variant {
+                Variant::SingleDecree => recording.input(id).map(|input| vec![input]),
+                _ => recording.inputs_of(id),
+            })
+            .collect::<Result<_, _>>()?;
+        let faults: Vec<Crash> = recording
+            .faults
+            .iter()
+            .map(|&(node, round, recover)| Crash {
+                node,
+                round,
+                recover,
+            })
+            .collect();
+        let invalid = faults
+            .iter()
+            .find(|f| f.node >= node_count || f.recover.is_some_and(|r| r <= f.round));
+        if let Some(fault) = invalid {
+            return Err(format!("Invalid fault of node {}", fault.node));
+        }
+
+        let network = Network::with_config(node_count, Some(0), config);
+        let mut system = Self::with_inputs(network, node_count, server_count, variant, inputs);
+        system.set_fault_schedule(faults);
+        system.set_skip_idle(recording.skip_idle);
+        system.replay(recording);
+        Ok(system)
+    }
+
+    fn simulate_with(
+        &mut self,
+        max_rounds: Option<usize>,
+        logger: &mut Logger,
+    ) -> SimulationReport {
+        System::simulate_with(self, max_rounds, logger)
+    }
+
+    fn decided(&self) -> bool {
+        System::decided(self)
+    }
+
+    fn step_with(&mut self, logger: &mut Logger) {
+        System::step_with(self, logger)
+    }
+
+    fn node_states(&self) -> Vec<NodeState> {
+        System::node_states(self)
+    }
+
+    fn record(&mut self) {
+        System::record(self)
+    }
+
+    fn recording(&self) -> Option<Recording> {
+        System::recording(self)
+    }
+
+    fn replay(&mut self, recording: Recording) {
+        System::replay(self, recording)
+    }
+}
+
+impl System {
+    pub fn new_rand_with(
+        node_count: usize,
+        server_count: usize,
+        seed: Option<u64>,
+        config: NetworkConfig,
+    ) -> Self {
+        Self::new_rand_from(node_count, server_count, seed, config, |rng| rng.gen())
+    }
+
+    pub fn new_multi_decree(
+        node_count: usize,
+        server_count: usize,
+        command_count: usize,
+        seed: Option<u64>,
+        config: NetworkConfig,
+    ) -> Self {
+        let generator = |rng: &mut StdRng| rng.gen();
+        Self::new_multi_decree_from(
+            node_count,
+            server_count,
+            command_count,
+            seed,
+            config,
+            generator,
+        )
+    }
+
+    pub fn new_multi_paxos(
+        node_count: usize,
+        server_count: usize,
+        proposer_count: usize,
+        command_count: usize,
+        seed: Option<u64>,
+        config: NetworkConfig,
+    ) -> Self {
+        let generator = |rng: &mut StdRng| rng.gen();
+        Self::new_multi_paxos_from(
+            node_count,
+            server_count,
+            proposer_count,
+            command_count,
+            seed,
+            config,
+            generator,
+        )
+    }
+}
+
+impl<V: Value + Display> System<V> {
+    /// The decisions of the network since `record`, with the inputs of the
+    /// clients, the fault schedule and what is needed to rebuild the nodes.
+    pub fn recording(&self) -> Option<Recording> {
+        let mut recording = self.network.recording()?.clone();
+        for command in &self.inputs {
+            if let Command::Defined(id, value) = command {
+                recording.inputs.push((id.client, value.to_string()));
+            }
+        }
+        for fault in &self.faults {
+            recording
+                .faults
+                .push((fault.node, fault.round, fault.recover));
+        }
+        recording.variant = Some(self.variant.to_string());
+        recording.skip_idle = self.skip_idle;
+        Some(recording)
+    }
+}
+
+impl<V: Value> System<V> {
+    /// Same as `crate::System::simulate`, but for every value type.
+    pub fn simulate(
+        &mut self,
+        max_rounds: Option<usize>,
+        log: Option<&str>,
+    ) -> io::Result<SimulationReport> {
+        let mut logger = Logger::new(log);
+        let report = self.simulate_with(max_rounds, &mut logger);
+        logger.flush()?;
+        Ok(report)
+    }
+
+    /// Same as `crate::System::simulate_with`, but for every value type.
+    pub fn simulate_with(
+        &mut self,
+        max_rounds: Option<usize>,
+        logger: &mut Logger,
+    ) -> SimulationReport {
+        let max_rounds = match max_rounds {
+            Some(nr) => nr,
+            None => usize::MAX,
+        };
+
+        while !self.decided() && self.network.round() < max_rounds && self.violation.is_none() {
+            if self.skip_idle {
+                let round = self.skip_idle_rounds(self.network.round(), max_rounds);
+                if round == max_rounds {
+                    break;
+                }
+            }
+            self.step_with(logger);
+        }
+
+        let termination = match (&self.violation, self.decided()) {
+            (Some(_), _) => Termination::SafetyViolation,
+            (None, true) => Termination::Decided,
+            (None, false) => Termination::MaxRounds,
+        };
+        let rounds = self.network.round();
+        let mut report = SimulationReport::new(&self.network, rounds, termination);
+        report.decided = self.decided_values();
+        report.decision_rounds = self.decision_rounds.clone();
+        report
+    }
+
+    /// Same as `crate::System::step`, but for every value type.
+    pub fn step(&mut self) -> Vec<TraceEvent> {
+        let mut logger = Logger::capturing();
+        self.step_with(&mut logger);
+        logger.take_events()
+    }
+
+    /// Runs exactly one round: faults, the message exchange, every running
+    /// node and the safety check. Idle rounds are never skipped.
+    pub fn step_with(&mut self, logger: &mut Logger) {
+        let cur_round = self.network.round();
+        logger.log_round(cur_round);
+        self.apply_faults(cur_round, logger);
+        self.network.exchange_messages();
+        if !self.network.events().is_empty() {
+            logger.log_actor(&self.network);
+            for event in self.network.events() {
+                logger.log_action(event);
+            }
+        }
+        for (id, node) in self.nodes.iter_mut().enumerate() {
+            if self.crashed[id].is_some() {
+                // Whatever reaches a crashed node is lost.
+                self.network.get_link_ref(id).borrow_mut().empty_buffer();
+            } else {
+                node.exec(logger);
+            }
+        }
+        self.check_safety(cur_round, logger);
+        for (id, node) in self.nodes.iter().enumerate() {
+            if self.decision_rounds[id].is_none() && !node.committed().is_empty() {
+                self.decision_rounds[id] = Some(cur_round);
+            }
+        }
+    }
+
+    pub fn node_states(&self) -> Vec<NodeState> {
+        self.nodes
+            .iter()
+            .enumerate()
+            .map(|(id, node)| NodeState::new(node, self.crashed[id].is_some(), node.state()))
+            .collect()
+    }
+
+    /// Packets the network still holds together with the round they are
+    /// due in, if known.
+    pub fn in_flight_packets(&self) -> Vec<(Option<usize>, Packet<Message<V>>)> {
+        self.network.in_flight_packets()
+    }
+
+    /// The round the next `step` runs.
+    pub fn round(&self) -> usize {
+        self.network.round()
+    }
+
+    pub fn drop_packet(&mut self, id: usize) -> bool {
+        self.network.drop_packet(id)
+    }
+
+    pub fn delay_packet(&mut self, id: usize, rounds: usize) -> bool {
+        self.network.delay_packet(id, rounds)
+    }
+
+    /// Distinct decisions of correct servers, for replicated logs the
+    /// longest log they committed.
+    fn decided_values(&self) -> Vec<String> {
+        if self.variant != Variant::SingleDecree {
+            let log = self.committed_logs(true).max_by_key(|log| log.len());
+            let log = log.unwrap_or_default();
+            return log.iter().map(|c| format!("{c:?}")).collect();
+        }
+        let mut decided: Vec<String> = (0..self.nodes.len())
+            .filter(|&id| self.is_correct(id) && self.nodes[id].get_type() == SERVER)
+            .filter(|&id| self.nodes[id].has_decided())
+            .map(|id| format!("{:?}", self.nodes[id].get_command()))
+            .collect();
+        decided.sort();
+        decided.dedup();
+        decided
+    }
+
+    /// Correct servers also have to know every committed slot.
+    pub fn decided(&self) -> bool {
+        for (id, node) in self.nodes.iter().enumerate() {
+            if self.is_correct(id) && !node.has_decided() {
+                return false;
+            }
+        }
+        if self.variant != Variant::SingleDecree {
+            let len = self.committed_logs(false).map(|log| log.len()).max();
+            return self.committed_logs(true).all(|log| Some(log.len()) >= len);
+        }
+        true
+    }
+
+    /// Every client proposes a value drawn by `generator` from the seeded rng.
+    pub fn new_rand_from<G>(
+        node_count: usize,
+        server_count: usize,
+        seed: Option<u64>,
+        config: NetworkConfig,
+        mut generator: G,
+    ) -> Self
+    where
+        G: FnMut(&mut StdRng) -> V,
+    {
         let mut rng = match seed {
             Some(seed) => StdRng::seed_from_u64(seed),
             None => StdRng::from_entropy(),
         };
 
-        let network = Network::new(true, node_count, seed, 10);
+        let network = Network::with_config(node_count, seed, config);
+        let inputs = (server_count..node_count)
+            .map(|_| vec![generator(&mut rng)])
+            .collect();
+        Self::with_inputs(
+            network,
+            node_count,
+            server_count,
+            Variant::SingleDecree,
+            inputs,
+        )
+    }
+
+    /// Replicated log, every client appends `command_count` commands.
+    pub fn new_multi_decree_from<G>(
+        node_count: usize,
+        server_count: usize,
+        command_count: usize,
+        seed: Option<u64>,
+        config: NetworkConfig,
+        mut generator: G,
+    ) -> Self
+    where
+        G: FnMut(&mut StdRng) -> V,
+    {
+        let mut rng = match seed {
+            Some(seed) => StdRng::seed_from_u64(seed),
+            None => StdRng::from_entropy(),
+        };
+
+        let network = Network::with_config(node_count, seed, config);
+        let inputs = (server_count..node_count)
+            .map(|_| (0..command_count).map(|_| generator(&mut rng)).collect())
+            .collect();
+        Self::with_inputs(
+            network,
+            node_count,
+            server_count,
+            Variant::MultiDecree,
+            inputs,
+        )
+    }
+
+    /// Multi-Paxos with `proposer_count` proposers after the servers, the
+    /// first of them leads until clients stop hearing from it.
+    pub fn new_multi_paxos_from<G>(
+        node_count: usize,
+        server_count: usize,
+        proposer_count: usize,
+        command_count: usize,
+        seed: Option<u64>,
+        config: NetworkConfig,
+        mut generator: G,
+    ) -> Self
+    where
+        G: FnMut(&mut StdRng) -> V,
+    {
+        assert!(
+            proposer_count > 0,
+            "Multi-Paxos needs at least one proposer"
+        );
+        let mut rng = match seed {
+            Some(seed) => StdRng::seed_from_u64(seed),
+            None => StdRng::from_entropy(),
+        };
+
+        let network = Network::with_config(node_count, seed, config);
+        let inputs = (server_count..node_count)
+            .map(|id| match id < server_count + proposer_count {
+                true => Vec::new(),
+                false => (0..command_count).map(|_| generator(&mut rng)).collect(),
+            })
+            .collect();
+        Self::with_inputs(
+            network,
+            node_count,
+            server_count,
+            Variant::MultiPaxos(proposer_count),
+            inputs,
+        )
+    }
+
+    /// Clients of `variant` with the values in `inputs`, one entry per client
+    /// id after the servers. Multi-Paxos proposers have no inputs.
+    fn with_inputs(
+        network: Network<Message<V>>,
+        node_count: usize,
+        server_count: usize,
+        variant: Variant,
+        mut inputs: Vec<Vec<V>>,
+    ) -> Self {
+        let proposers: Vec<usize> = match variant {
+            Variant::MultiPaxos(count) => (server_count..server_count + count).collect(),
+            _ => Vec::new(),
+        };
+        Self::new(
+            network,
+            node_count,
+            server_count,
+            variant,
+            |id, link, servers| {
+                let values = std::mem::take(&mut inputs[id - server_count]);
+                let mut commands: Vec<Command<V>> = values
+                    .into_iter()
+                    .enumerate()
+                    .map(|(seq, value)| Command::new(id, seq, value))
+                    .collect();
+                match variant {
+                    Variant::SingleDecree => {
+                        let command = commands.remove(0);
+                        Box::new(Client::new(id, link, servers, command))
+                    }
+                    Variant::MultiDecree => {
+                        Box::new(Client::new_sequence(id, link, servers, commands))
+                    }
+                    Variant::MultiPaxos(_) if proposers.contains(&id) => {
+                        Box::new(Proposer::new(id, link, servers))
+                    }
+                    Variant::MultiPaxos(_) => {
+                        Box::new(Requester::new(id, link, proposers.clone(), commands))
+                    }
+                }
+            },
+        )
+    }
+
+    fn new<F>(
+        network: Network<Message<V>>,
+        node_count: usize,
+        server_count: usize,
+        variant: Variant,
+        mut new_client: F,
+    ) -> Self
+    where
+        F: FnMut(usize, LinkInterface<V>, ServerList) -> Box<dyn Node<V>>,
+    {
         let mut id = 0;
-        let mut nodes: Vec<Box<dyn Node>> = Vec::new();
+        let mut nodes: Vec<Box<dyn Node<V>>> = Vec::new();
         let servers = Rc::new(RefCell::new(Vec::with_capacity(server_count)));
 
         while id < server_count {
@@ -97,81 +754,187 @@ impl crate::System for System {
         }
 
         while id < node_count {
-            nodes.push(Box::new(Client::new_rand(
-                id,
-                network.get_link_ref(id),
-                servers.clone(),
-                &mut rng,
-            )));
+            nodes.push(new_client(id, network.get_link_ref(id), servers.clone()));
             id += 1
         }
 
-        System { nodes, network }
+        let crashed = vec![None; nodes.len()];
+        let inputs: Vec<Command<V>> = nodes.iter().flat_map(|node| node.inputs()).collect();
+        System {
+            decision_rounds: vec![None; nodes.len()],
+            nodes,
+            network,
+            faults: Vec::new(),
+            crashed,
+            skip_idle: false,
+            variant,
+            checker: SafetyChecker::new(inputs.clone()),
+            inputs,
+            violation: None,
+        }
     }
 
-    fn simulate(&mut self, max_rounds: Option<usize>, log: Option<&str>) {
-        let max_rounds = match max_rounds {
-            Some(nr) => nr,
-            None => MAX,
-        };
+    pub fn set_scheduler(&mut self, scheduler: Box<dyn Scheduler<Message<V>>>) {
+        self.network.set_scheduler(scheduler);
+    }
 
-        let mut logger = Logger::new(log);
+    pub fn record(&mut self) {
+        self.network.record()
+    }
+
+    /// Replaces the scheduler, so it has to be called after `set_scheduler`.
+    pub fn replay(&mut self, recording: Recording) {
+        self.network.replay(recording)
+    }
 
-        let mut cur_round = 0;
-        while !self.decided() && cur_round < max_rounds {
-            logger.log_round(cur_round);
-            self.network.exchange_messages();
-            for (_, node) in self.nodes.iter_mut().enumerate() {
-                node.exec(&mut logger);
+    /// Lets `simulate` jump over rounds in which no node acts and no packet
+    /// is delivered. Skipped rounds do not show up in the log.
+    pub fn set_skip_idle(&mut self, skip_idle: bool) {
+        self.skip_idle = skip_idle;
+    }
+
+    pub fn set_fault_schedule(&mut self, faults: Vec<Crash>) {
+        for fault in faults.iter() {
+            assert!(
+                fault.node < self.nodes.len(),
+                "Invalid node id {}",
+                fault.node
+            );
+            if let Some(recover) = fault.recover {
+                assert!(fault.round < recover, "Node recovers before it crashes");
             }
+        }
+        self.faults = faults;
+    }
+
+    /// A node is correct unless the crash it is down for has no recovery.
+    pub fn is_correct(&self, id: usize) -> bool {
+        self.crashed[id].is_none_or(|crash| crash.recover.is_some())
+    }
 
-            cur_round += 1;
+    fn skip_idle_rounds(&mut self, round: usize, max_rounds: usize) -> usize {
+        let idle = (0..self.nodes.len())
+            .filter(|&id| self.crashed[id].is_none())
+            .map(|id| self.nodes[id].idle_rounds())
+            .min()
+            .unwrap_or(usize::MAX);
+        let next_fault = self
+            .faults
+            .iter()
+            .flat_map(|f| [Some(f.round), f.recover])
+            .flatten()
+            .filter(|&r| r >= round)
+            .min();
+        let target = [self.network.next_event(), next_fault]
+            .into_iter()
+            .flatten()
+            .fold(round.saturating_add(idle).min(max_rounds), usize::min);
+
+        if target > round {
+            self.network.skip_to(target);
+            for (id, node) in self.nodes.iter_mut().enumerate() {
+                if self.crashed[id].is_none() {
+                    node.skip_rounds(target - round);
+                }
+            }
         }
+        target
     }
 
-    fn decided(&self) -> bool {
-        for node in self.nodes.iter() {
-            if !node.has_decided() {
-                return false;
+    /// A crashed node only recovers with the fault that crashed it, faults
+    /// starting while it is down are ignored.
+    fn apply_faults(&mut self, round: usize, logger: &mut Logger) {
+        for fault in self.faults.iter() {
+            let node = &mut self.nodes[fault.node];
+            match self.crashed[fault.node] {
+                None if fault.round == round => {
+                    logger.log_actor(node);
+                    logger.log_action(&Action::<V>::Crash);
+                    node.crash();
+                    self.crashed[fault.node] = Some(*fault);
+                }
+                Some(crash) if crash.recover == Some(round) => {
+                    logger.log_actor(node);
+                    logger.log_action(&Action::<V>::Recover);
+                    node.recover();
+                    self.crashed[fault.node] = None;
+                }
+                _ => {}
             }
         }
-        true
     }
-}
 
-impl System {
-    pub fn client_commands(&self) -> Vec<Command> {
+    /// Checks agreement, validity and stability of all running servers.
+    /// The run stops at the first violation.
+    fn check_safety(&mut self, round: usize, logger: &mut Logger) {
+        for (id, node) in self.nodes.iter().enumerate() {
+            if self.crashed[id].is_some() || self.violation.is_some() {
+                continue;
+            }
+            if let Err(violation) = self.checker.observe(round, id, &node.decisions()) {
+                logger.log_actor(&self.checker);
+                logger.log_action(&violation);
+                self.violation = Some(violation);
+            }
+        }
+    }
+
+    /// The first safety violation of the run, if any.
+    pub fn safety_violation(&self) -> Option<&SafetyViolation<V>> {
+        self.violation.as_ref()
+    }
+
+    pub fn client_commands(&self) -> Vec<Command<V>> {
         let mut ret = Vec::new();
         for node in self.nodes.iter() {
-            match node.get_type() {
-                CLIENT => ret.push(node.get_command()),
-                _ => (),
+            if node.get_type() == CLIENT {
+                ret.push(node.get_command())
             }
         }
 
-        return ret;
+        ret
+    }
+
+    fn committed_logs(&self, servers: bool) -> impl Iterator<Item = Vec<Command<V>>> + '_ {
+        (0..self.nodes.len())
+            .filter(move |&id| {
+                self.is_correct(id) && (self.nodes[id].get_type() == SERVER) == servers
+            })
+            .map(|id| self.nodes[id].committed())
     }
 
-    pub fn servers_agree(&self) -> Option<Command> {
+    /// Number of packets sent over the network so far.
+    pub fn messages_sent(&self) -> usize {
+        self.network.sent()
+    }
+
+    /// The log all correct servers committed, if they agree.
+    pub fn servers_agree_on_log(&self) -> Option<Vec<Command<V>>> {
+        let mut logs = self.committed_logs(true);
+        let first = logs.next()?;
+        logs.all(|log| log == first).then_some(first)
+    }
+
+    /// No two committed logs contradict each other.
+    pub fn logs_consistent(&self) -> bool {
+        let logs: Vec<_> = self.nodes.iter().map(|node| node.committed()).collect();
+        let longest = logs.iter().max_by_key(|log| log.len()).cloned();
+        let longest = longest.unwrap_or_default();
+        logs.iter().all(|log| longest.starts_with(log))
+    }
+
+    pub fn servers_agree(&self) -> Option<Command<V>> {
         let mut any_command = None;
         for node in self.nodes.iter() {
-            match node.get_type() {
-                SERVER => {
-                    any_command = Some(node.get_command());
-                    break;
-                }
-                _ => (),
+            if node.get_type() == SERVER {
+                any_command = Some(node.get_command());
+                break;
             }
         }
 
         for node in self.nodes.iter() {
-            match node.get_type() {
-                SERVER => {
-                    if node.get_command() != any_command.unwrap() {
-                        return None;
-                    }
-                }
-                _ => (),
+            if node.get_type() == SERVER && Some(node.get_command()) != any_command {
+                return None;
             }
         }
         any_command
@@ -180,8 +943,41 @@ impl System {
 
 #[cfg(test)]
 mod test {
+    use crate::network::{Fifo, LatencyOrder, Packet, RandomOrder, Scheduler, Starve};
+    use crate::sink::MemorySink;
+    use crate::trace::TraceFormat;
     use crate::System;
     use crate::*;
+    use rand::rngs::StdRng;
+    use std::ops::Range;
+
+    /// Simulates the system `build` makes for every seed and checks that it
+    /// decided safely with the correct servers agreeing on their log.
+    fn decides_and_agrees<V: paxos::Value + std::fmt::Display>(
+        seeds: Range<u64>,
+        max_rounds: usize,
+        build: impl Fn(u64) -> paxos::System<V>,
+    ) -> Vec<paxos::System<V>> {
+        let check = |seed| {
+            let mut system = build(seed);
+            system.simulate(Some(max_rounds), None).unwrap();
+            assert!(system.decided(), "seed {seed}");
+            assert!(system.safety_violation().is_none(), "seed {seed}");
+            assert!(system.logs_consistent(), "seed {seed}");
+            assert!(system.servers_agree_on_log().is_some(), "seed {seed}");
+            system
+        };
+        seeds.map(check).collect()
+    }
+
+    fn decided_server_commands(system: &paxos::System) -> Vec<paxos::Command> {
+        system
+            .nodes
+            .iter()
+            .filter(|node| node.get_type() == paxos::SERVER && node.has_decided())
+            .map(|node| node.get_command())
+            .collect()
+    }
 
     #[test]
     fn paxos_rng_is_deterministic() {
@@ -193,28 +989,689 @@ mod test {
 
     #[test]
     fn paxos_3() {
+        let sink = MemorySink::new();
+        let mut logger = Logger::with_sink(Box::new(sink.clone()), TraceFormat::Text);
         let mut system: paxos::System = System::new_rand(3, 1, None);
-        system.simulate(None, Some("paxos_3"));
+        let report = system.simulate_with(None, &mut logger);
+        let trace = sink.contents();
+        assert!(trace.starts_with("Iteration nr: 0\n\tServer #0\n"));
+        let rounds = trace.lines().filter(|l| l.starts_with("Iteration nr: "));
+        assert_eq!(rounds.count(), report.rounds);
+        assert!(trace.contains("\t\tdecides for "));
     }
 
     #[test]
     fn paxos_terminates() {
         let seed = 420;
         let mut system: paxos::System = System::new_rand(3, 1, Some(seed));
-        system.simulate(None, Some("paxos_terminates"));
+        let report = system.simulate(None, Some("paxos_terminates")).unwrap();
+        assert_eq!(report.termination, Termination::Decided);
+        assert_eq!(report.rounds, system.network.round());
+        assert_eq!(report.decided.len(), 1);
+        assert!(report.decision_rounds[0].is_some());
+        assert_eq!(
+            report.message_counts.values().sum::<usize>(),
+            report.messages_sent
+        );
+        assert!(report.message_counts["Ask"] >= report.message_counts["Ok"]);
+        assert!(report.messages_delivered <= report.messages_sent);
+    }
+
+    #[test]
+    fn paxos_writes_json_lines() {
+        let sink = MemorySink::new();
+        let mut logger = Logger::with_sink(Box::new(sink.clone()), TraceFormat::JsonLines);
+        let mut system: paxos::System = System::new_rand(3, 1, Some(420));
+        let report = system.simulate_with(None, &mut logger);
+        let trace = sink.contents();
+        assert!(trace
+            .lines()
+            .all(|line| line.starts_with("{\"round\":") && line.ends_with('}')));
+        let sends = trace.lines().filter(|l| l.contains("\"action\":\"send\""));
+        assert_eq!(sends.count(), report.messages_sent);
+        let decide = trace.lines().find(|l| l.contains("\"action\":\"decide\""));
+        let decide = decide.unwrap();
+        assert!(decide.contains("\"actor\":\"Server\""));
+        assert!(decide.contains("\"slot\":0"));
     }
 
     #[test]
     fn paxos_agrees() {
         let mut system: paxos::System = System::new_rand(3, 1, None);
-        system.simulate(None, Some("paxos_agrees"));
-        assert!(system.servers_agree() != None)
+        system.simulate(None, Some("paxos_agrees")).unwrap();
+        assert!(system.servers_agree().is_some())
+    }
+
+    #[test]
+    fn paxos_lossy_servers_agree() {
+        use crate::network::NetworkConfig;
+
+        let mut config = NetworkConfig::new(true, 10);
+        config.drop_probability = 0.1;
+        let mut system = paxos::System::new_rand_with(7, 3, Some(1337), config);
+        system.simulate(Some(2000), Some("paxos_lossy")).unwrap();
+        assert!(system.network.dropped() > 0);
+        let decisions = decided_server_commands(&system);
+        assert!(!decisions.is_empty());
+        assert!(decisions.iter().all(|c| *c == decisions[0]));
+    }
+
+    #[test]
+    fn paxos_replays_recorded_run() {
+        use crate::network::NetworkConfig;
+        use crate::trace::first_difference;
+
+        let trace = |system: &mut paxos::System| {
+            let sink = MemorySink::new();
+            let mut logger = Logger::with_sink(Box::new(sink.clone()), TraceFormat::Text);
+            let report = system.simulate_with(Some(3000), &mut logger);
+            (report, sink.contents())
+        };
+
+        let mut config = NetworkConfig::new(true, 10);
+        config.drop_probability = 0.1;
+        config.duplicate_probability = 0.2;
+        config.max_jitter = 3;
+        let mut system = paxos::System::new_rand_with(5, 2, Some(7), config);
+        system.set_scheduler(Box::new(RandomOrder::new(3)));
+        system.record();
+        let (report, recorded) = trace(&mut system);
+        let recording = system.recording().unwrap().clone();
+        assert!(!recording.dropped.is_empty() && !recording.duplicated.is_empty());
+
+        // Neither the rng nor the latencies of the new network matter.
+        let config = NetworkConfig::new(true, 1);
+        let mut system = paxos::System::new_rand_with(5, 2, Some(7), config.clone());
+        system.replay(recording);
+        let (replayed_report, replayed) = trace(&mut system);
+        assert_eq!(first_difference(&recorded, &replayed), None);
+        assert_eq!(replayed_report, report);
+
+        let mut system = paxos::System::new_rand_with(5, 2, Some(7), config);
+        let (_, diverged) = trace(&mut system);
+        assert!(first_difference(&recorded, &diverged).is_some());
+    }
+
+    #[test]
+    fn paxos_replays_variant_and_faults_from_recording() {
+        use crate::network::{NetworkConfig, Recording};
+        use crate::trace::first_difference;
+
+        let trace = |system: &mut paxos::System| {
+            let sink = MemorySink::new();
+            let mut logger = Logger::with_sink(Box::new(sink.clone()), TraceFormat::Text);
+            let report = system.simulate_with(Some(20_000), &mut logger);
+            (report, sink.contents())
+        };
+
+        for multi_paxos in [false, true] {
+            let mut config = NetworkConfig::new(true, 10);
+            config.drop_probability = 0.05;
+            let (nodes, mut system) = match multi_paxos {
+                false => (5, paxos::System::new_multi_decree(5, 3, 3, Some(5), config)),
+                true => (
+                    7,
+                    paxos::System::new_multi_paxos(7, 3, 2, 3, Some(5), config),
+                ),
+            };
+            system.set_fault_schedule(vec![
+                paxos::Crash {
+                    node: 2,
+                    round: 20,
+                    recover: Some(200),
+                },
+                paxos::Crash {
+                    node: 2,
+                    round: 300,
+                    recover: None,
+                },
+            ]);
+            system.set_skip_idle(true);
+            system.record();
+            let (report, recorded) = trace(&mut system);
+            assert!(recorded.contains("crashes"));
+            let text = system.recording().unwrap().to_text();
+            let recording = Recording::parse(&text).unwrap();
+
+            let config = NetworkConfig::new(true, 1);
+            let mut replayed: paxos::System =
+                System::from_recording(nodes, 3, config, recording).unwrap();
+            let (replayed_report, replayed_trace) = trace(&mut replayed);
+            assert_eq!(first_difference(&recorded, &replayed_trace), None);
+            assert_eq!(replayed_report, report);
+            assert_eq!(
+                replayed.servers_agree_on_log(),
+                system.servers_agree_on_log()
+            );
+        }
+
+        let config = NetworkConfig::new(true, 1);
+        let recording = Recording::parse("input 1 true\nfault 5 3").unwrap();
+        let system = <paxos::System as System>::from_recording(2, 1, config, recording);
+        assert!(system.is_err());
+    }
+
+    #[test]
+    fn paxos_steps_round_by_round() {
+        let mut system: paxos::System = System::new_rand(3, 1, Some(3));
+        let events = system.step();
+        assert!(events.iter().all(|e| e.round == 0));
+        assert!(events.iter().any(|e| e.action == "send"));
+        // Packets sent in a round are scheduled in the next one.
+        let unsent = system.in_flight_packets();
+        assert!(!unsent.is_empty() && unsent.iter().all(|(due, _)| due.is_none()));
+        system.step();
+        let in_flight = system.in_flight_packets();
+        let scheduled: Vec<_> = in_flight
+            .iter()
+            .filter(|(_, p)| p.id < unsent.len())
+            .collect();
+        assert_eq!(scheduled.len(), unsent.len());
+        assert!(scheduled
+            .iter()
+            .all(|(due, _)| due.is_some_and(|due| due >= 1)));
+
+        while !system.decided() {
+            assert!(system.network.round() < 3000);
+            system.step();
+        }
+        let states = system.node_states();
+        assert_eq!(states[0].kind, "Server");
+        assert_ne!(states[0].variable("C[0]"), Some("⊥"));
+        assert_eq!(states[1].kind, "Client");
+        assert!(states[1].variable("t").is_some());
+    }
+
+    #[test]
+    fn paxos_tolerates_duplicates() {
+        use crate::network::NetworkConfig;
+
+        let systems = decides_and_agrees(0..20, 5000, |seed| {
+            let mut config = NetworkConfig::new(true, 10);
+            config.duplicate_probability = 0.3;
+            config.max_jitter = 5;
+            paxos::System::new_rand_with(7, 3, Some(seed), config)
+        });
+        for system in systems {
+            assert!(system.network.duplicated() > 0);
+            assert!(system.network.redelivered() > 0);
+            assert!(system.network.delivered() <= system.network.sent());
+        }
+    }
+
+    #[test]
+    fn paxos_safe_under_partition() {
+        use crate::network::{NetworkConfig, Partition, PartitionPolicy};
+
+        for policy in [PartitionPolicy::Drop, PartitionPolicy::Hold] {
+            decides_and_agrees(0..10, 2000, |seed| {
+                let mut config = NetworkConfig::new(true, 10);
+                let partition = Partition::parse("{0,3,4} | {1,2,5,6}", 0, Some(300), policy);
+                config.partitions.push(partition.unwrap());
+                paxos::System::new_rand_with(7, 3, Some(seed), config)
+            });
+        }
+    }
+
+    #[test]
+    fn paxos_survives_crash_stop() {
+        let systems = decides_and_agrees(0..10, 5000, |seed| {
+            let mut system: paxos::System = System::new_rand(5, 3, Some(seed));
+            system.set_fault_schedule(vec![
+                paxos::Crash {
+                    node: 0,
+                    round: 0,
+                    recover: None,
+                },
+                paxos::Crash {
+                    node: 4,
+                    round: 3,
+                    recover: None,
+                },
+            ]);
+            system
+        });
+        for system in systems {
+            assert!(!system.is_correct(0));
+            assert_eq!(decided_server_commands(&system).len(), 2);
+        }
+    }
+
+    #[test]
+    fn paxos_survives_crash_recovery() {
+        let systems = decides_and_agrees(0..10, 5000, |seed| {
+            let mut system: paxos::System = System::new_rand(5, 3, Some(seed));
+            system.set_fault_schedule(vec![paxos::Crash {
+                node: 1,
+                round: 5,
+                recover: Some(100),
+            }]);
+            system
+        });
+        assert!(systems.iter().all(|system| system.is_correct(1)));
+    }
+
+    #[test]
+    fn paxos_server_relearns_decisions_after_recovery() {
+        let mut system: paxos::System = System::new_rand(5, 3, Some(4));
+        system.simulate(Some(5000), None).unwrap();
+        assert!(system.decided());
+        let before = system.node_states()[0].clone();
+        assert_eq!(before.variable("decided[0]"), Some("true"));
+
+        let round = system.round();
+        system.set_fault_schedule(vec![paxos::Crash {
+            node: 0,
+            round,
+            recover: Some(round + 1),
+        }]);
+        system.step();
+        system.step();
+        let recovered = system.node_states()[0].clone();
+        assert_eq!(recovered.variable("decided[0]"), Some("false"));
+        for var in ["t_max[0]", "t_store[0]", "C[0]"] {
+            assert_eq!(recovered.variable(var), before.variable(var), "{var}");
+        }
+        while system.node_states()[0].variable("decided[0]") != Some("true") {
+            assert!(system.round() < round + 1000, "Decision was not repeated");
+            system.step();
+        }
+    }
+
+    #[test]
+    fn paxos_recovers_only_from_the_active_crash() {
+        let mut system: paxos::System = System::new_rand(5, 3, Some(2));
+        system.set_fault_schedule(vec![
+            paxos::Crash {
+                node: 2,
+                round: 2,
+                recover: None,
+            },
+            paxos::Crash {
+                node: 2,
+                round: 10,
+                recover: Some(12),
+            },
+        ]);
+        for _ in 0..20 {
+            system.step();
+        }
+        assert!(!system.is_correct(2));
+        assert!(system.node_states()[2].crashed);
+        system.simulate(Some(5000), None).unwrap();
+        assert!(system.decided());
+    }
+
+    #[test]
+    fn paxos_skip_idle_matches_full_run() {
+        use crate::network::NetworkConfig;
+
+        for seed in 0..10 {
+            let run = |skip_idle| {
+                let config = NetworkConfig::new(true, 1000);
+                let mut system = paxos::System::new_rand_with(4, 3, Some(seed), config);
+                system.set_fault_schedule(vec![paxos::Crash {
+                    node: 2,
+                    round: 700,
+                    recover: Some(1500),
+                }]);
+                system.set_skip_idle(skip_idle);
+                system.simulate(Some(100_000), None).unwrap();
+                assert!(system.decided());
+                (system.network.round(), system.servers_agree())
+            };
+            assert_eq!(run(true), run(false));
+        }
+    }
+
+    #[test]
+    fn paxos_agrees_with_heavy_tailed_latencies() {
+        use crate::network::{LatencyModel, NetworkConfig};
+
+        decides_and_agrees(0..10, 20_000, |seed| {
+            let mut config = NetworkConfig::new(true, 0);
+            config.latency = LatencyModel::PerPacket(Box::new(LatencyModel::Pareto {
+                scale: 2.0,
+                shape: 1.5,
+            }));
+            paxos::System::new_rand_with(5, 3, Some(seed), config)
+        });
+    }
+
+    #[test]
+    fn paxos_decides_after_gst() {
+        use crate::network::{LatencyModel, NetworkConfig, Timing};
+
+        decides_and_agrees(0..10, 20_000, |seed| {
+            let mut config = NetworkConfig::new(true, 0);
+            config.timing = Timing::PartiallySynchronous { gst: 500, delta: 5 };
+            config.latency =
+                LatencyModel::PerPacket(Box::new(LatencyModel::Exponential { mean: 200.0 }));
+            paxos::System::new_rand_with(4, 3, Some(seed), config)
+        });
+    }
+
+    #[test]
+    fn paxos_decides_with_builtin_schedulers() {
+        decides_and_agrees(0..15, 20_000, |seed| {
+            let scheduler: Box<dyn Scheduler<paxos::Message>> = match seed % 3 {
+                0 => Box::new(Fifo::new()),
+                1 => Box::new(RandomOrder::new(3)),
+                _ => Box::new(Starve::new(0, Some(300), Box::new(LatencyOrder::new()))),
+            };
+            let mut system: paxos::System = System::new_rand(4, 3, Some(seed));
+            system.set_scheduler(scheduler);
+            system
+        });
+    }
+
+    /// Holds back every proposal until an ask with a higher ticket reached
+    /// the same server, so no proposal is ever accepted.
+    struct DuellingProposers {
+        inner: LatencyOrder<paxos::Message>,
+        proposals: Vec<Packet<paxos::Message>>,
+    }
+
+    impl Scheduler<paxos::Message> for DuellingProposers {
+        fn push(&mut self, due: usize, packet: Packet<paxos::Message>) {
+            self.inner.push(due, packet)
+        }
+
+        fn deliver(&mut self, round: usize, rng: &mut StdRng) -> Vec<Packet<paxos::Message>> {
+            let mut packets = Vec::new();
+            for packet in self.inner.deliver(round, rng) {
+                match packet.content {
+                    paxos::Message::Propose(_, _, _) => self.proposals.push(packet),
+                    paxos::Message::Ask(_, ticket) => {
+                        let server = packet.receiver;
+                        packets.push(packet);
+                        let (outdated, held): (Vec<_>, Vec<_>) =
+                            self.proposals.drain(..).partition(|p| {
+                                p.receiver == server
+                                    && matches!(p.content, paxos::Message::Propose(_, t, _) if t < ticket)
+                            });
+                        self.proposals = held;
+                        packets.extend(outdated);
+                    }
+                    _ => packets.push(packet),
+                }
+            }
+            packets
+        }
+
+        fn next_delivery(&self, round: usize) -> Option<usize> {
+            self.inner.next_delivery(round)
+        }
+
+        fn len(&self) -> usize {
+            self.inner.len() + self.proposals.len()
+        }
+
+        fn pending(&self) -> Vec<(Option<usize>, &Packet<paxos::Message>)> {
+            let proposals = self.proposals.iter().map(|packet| (None, packet));
+            self.inner.pending().into_iter().chain(proposals).collect()
+        }
+
+        fn remove(&mut self, id: usize) -> Option<Packet<paxos::Message>> {
+            let index = self.proposals.iter().position(|p| p.id == id);
+            match index {
+                Some(index) => Some(self.proposals.remove(index)),
+                None => self.inner.remove(id),
+            }
+        }
+    }
+
+    #[test]
+    fn paxos_duelling_proposers_livelock() {
+        let mut system: paxos::System = System::new_rand(5, 3, Some(42));
+        system.set_scheduler(Box::new(DuellingProposers {
+            inner: LatencyOrder::new(),
+            proposals: Vec::new(),
+        }));
+        let report = system.simulate(Some(5000), Some("paxos_livelock")).unwrap();
+        assert!(!system.decided());
+        assert_eq!(system.network.round(), 5000);
+        assert_eq!(report.termination, Termination::MaxRounds);
+        assert!(report.decided.is_empty());
+    }
+
+    #[test]
+    fn paxos_multi_decree_logs_agree() {
+        use crate::network::NetworkConfig;
+
+        let systems = decides_and_agrees(0..10, 20_000, |seed| {
+            let config = NetworkConfig::new(true, 10);
+            paxos::System::new_multi_decree(5, 3, 4, Some(seed), config)
+        });
+        for system in systems {
+            let log = system.servers_agree_on_log().unwrap();
+            assert!(log.iter().all(|c| *c != paxos::Command::Undefined));
+            for client in 3..5 {
+                for seq in 0..4 {
+                    let id = paxos::CommandId { client, seq };
+                    assert!(log.iter().any(|c| c.id() == Some(id)));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn paxos_multi_decree_survives_crash_recovery() {
+        use crate::network::NetworkConfig;
+
+        decides_and_agrees(0..10, 20_000, |seed| {
+            let mut config = NetworkConfig::new(true, 10);
+            config.drop_probability = 0.05;
+            let mut system = paxos::System::new_multi_decree(5, 3, 3, Some(seed), config);
+            system.set_fault_schedule(vec![paxos::Crash {
+                node: 2,
+                round: 20,
+                recover: Some(200),
+            }]);
+            system
+        });
+    }
+
+    #[test]
+    fn paxos_multi_paxos_logs_agree() {
+        use crate::network::NetworkConfig;
+
+        let systems = decides_and_agrees(0..10, 20_000, |seed| {
+            let mut config = NetworkConfig::new(true, 10);
+            config.drop_probability = 0.05;
+            paxos::System::new_multi_paxos(7, 3, 2, 4, Some(seed), config)
+        });
+        for system in systems {
+            let log = system.servers_agree_on_log().unwrap();
+            assert_eq!(log.iter().filter(|c| c.id().is_some()).count(), 8);
+            for client in 5..7 {
+                for seq in 0..4 {
+                    let id = paxos::CommandId { client, seq };
+                    assert_eq!(log.iter().filter(|c| c.id() == Some(id)).count(), 1);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn paxos_multi_paxos_reelects_after_leader_crash() {
+        use crate::network::NetworkConfig;
+
+        let systems = decides_and_agrees(0..10, 20_000, |seed| {
+            let config = NetworkConfig::new(true, 10);
+            let mut system = paxos::System::new_multi_paxos(7, 3, 2, 6, Some(seed), config);
+            system.set_fault_schedule(vec![paxos::Crash {
+                node: 3,
+                round: 40,
+                recover: None,
+            }]);
+            system
+        });
+        for system in systems {
+            let log = system.servers_agree_on_log().unwrap();
+            for client in 5..7 {
+                for seq in 0..6 {
+                    let id = paxos::CommandId { client, seq };
+                    assert_eq!(log.iter().filter(|c| c.id() == Some(id)).count(), 1);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn paxos_multi_paxos_sends_fewer_messages() {
+        use crate::network::NetworkConfig;
+
+        let per_command = |systems: Vec<paxos::System>| -> usize {
+            let per_command =
+                |s: &paxos::System| s.messages_sent() / s.servers_agree_on_log().unwrap().len();
+            systems.iter().map(per_command).sum()
+        };
+        let leaderless = per_command(decides_and_agrees(0..10, 20_000, |seed| {
+            let config = NetworkConfig::new(true, 10);
+            paxos::System::new_multi_decree(5, 3, 4, Some(seed), config)
+        }));
+        let leader = per_command(decides_and_agrees(0..10, 20_000, |seed| {
+            let config = NetworkConfig::new(true, 10);
+            paxos::System::new_multi_paxos(6, 3, 1, 4, Some(seed), config)
+        }));
+        assert!(leader < leaderless);
+    }
+
+    #[test]
+    fn paxos_agrees_on_strings() {
+        use crate::network::NetworkConfig;
+
+        let systems = decides_and_agrees(0..10, 20_000, |seed| {
+            let mut next = 0;
+            let generator = |_: &mut StdRng| {
+                next += 1;
+                format!("set x {next}")
+            };
+            let config = NetworkConfig::new(true, 10);
+            paxos::System::new_multi_decree_from(5, 3, 3, Some(seed), config, generator)
+        });
+        for system in systems {
+            let log = system.servers_agree_on_log().unwrap();
+            for op in 1..=6 {
+                let command = format!("set x {op}");
+                assert_eq!(
+                    log.iter().filter(|c| c.value() == Some(&command)).count(),
+                    1
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn paxos_equal_rounds_of_different_clients_are_ordered() {
+        use crate::network::Network;
+        use paxos::{Command, Message, Node, Server, Ticket};
+
+        let mut network = Network::new(false, 3, Some(0), 0);
+        let mut server: Server<bool> = Server::new(0, network.get_link_ref(0));
+        let mut logger = Logger::new(None);
+        let (first, second) = (Ticket::new(1, 1), Ticket::new(1, 2));
+        assert!(first < second);
+
+        let clients = [(1, first, true), (2, second, false)];
+        for (client, ticket, _) in clients {
+            let ask = Message::Ask(0, ticket);
+            network.get_link_ref(client).borrow_mut().enqueue(0, ask);
+        }
+        network.exchange_messages();
+        server.exec(&mut logger);
+        for (client, ticket, value) in clients {
+            let propose = Message::Propose(0, ticket, Command::new(client, 0, value));
+            network
+                .get_link_ref(client)
+                .borrow_mut()
+                .enqueue(0, propose);
+        }
+        network.exchange_messages();
+        server.exec(&mut logger);
+        network.exchange_messages();
+
+        for (client, ticket, _) in clients {
+            let successes = network
+                .get_link_ref(client)
+                .borrow_mut()
+                .empty_buffer()
+                .into_iter()
+                .filter(|p| matches!(p.content, Message::Success(..)))
+                .count();
+            assert_eq!(successes, usize::from(ticket == second));
+        }
     }
 
     #[test]
     fn paxos_valid_history() {
         let seed = 420;
         let mut system: paxos::System = System::new_rand(3, 1, Some(seed));
-        system.simulate(None, Some("paxos_valid"));
+        system.simulate(None, Some("paxos_valid")).unwrap();
+        assert!(system.decided());
+        assert!(system.safety_violation().is_none());
+    }
+
+    /// Flips the value of every `Execute` that reaches server 0.
+    struct CorruptExecutes {
+        inner: LatencyOrder<paxos::Message>,
+    }
+
+    impl Scheduler<paxos::Message> for CorruptExecutes {
+        fn push(&mut self, due: usize, packet: Packet<paxos::Message>) {
+            self.inner.push(due, packet)
+        }
+
+        fn deliver(&mut self, round: usize, rng: &mut StdRng) -> Vec<Packet<paxos::Message>> {
+            let mut packets = self.inner.deliver(round, rng);
+            for packet in packets.iter_mut() {
+                if let paxos::Message::Execute(slot, paxos::Command::Defined(id, value)) =
+                    packet.content
+                {
+                    if packet.receiver == 0 {
+                        let command = paxos::Command::Defined(id, !value);
+                        packet.content = paxos::Message::Execute(slot, command);
+                    }
+                }
+            }
+            packets
+        }
+
+        fn next_delivery(&self, round: usize) -> Option<usize> {
+            self.inner.next_delivery(round)
+        }
+
+        fn len(&self) -> usize {
+            self.inner.len()
+        }
+
+        fn pending(&self) -> Vec<(Option<usize>, &Packet<paxos::Message>)> {
+            self.inner.pending()
+        }
+
+        fn remove(&mut self, id: usize) -> Option<Packet<paxos::Message>> {
+            self.inner.remove(id)
+        }
+    }
+
+    #[test]
+    fn paxos_checker_catches_corrupted_decisions() {
+        use paxos::checker::Violation;
+
+        for seed in 0..10 {
+            let mut system: paxos::System = System::new_rand(5, 3, Some(seed));
+            system.set_scheduler(Box::new(CorruptExecutes {
+                inner: LatencyOrder::new(),
+            }));
+            let report = system
+                .simulate(Some(5000), Some("paxos_corrupted"))
+                .unwrap();
+            assert_eq!(report.termination, Termination::SafetyViolation);
+            let violation = system.safety_violation().unwrap();
+            assert_eq!(violation.round + 1, system.network.round());
+            assert!(violation.node < 3);
+            assert!(!matches!(violation.violation, Violation::Stability { .. }));
+        }
     }
 }
@@ -0,0 +1,169 @@
+use super::*;
+use std::collections::HashMap;
+
+/// A safety property of consensus that a node broke.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Violation<V> {
+    /// The node decided `value` for `slot`, another node decided `other`.
+    Agreement {
+        slot: Slot,
+        value: Command<V>,
+        other: Command<V>,
+    },
+    /// No client proposed the decided `value`.
+    Validity { slot: Slot, value: Command<V> },
+    /// The node changed its decision for `slot` from `old` to `new`.
+    Stability {
+        slot: Slot,
+        old: Command<V>,
+        new: Command<V>,
+    },
+}
+
+#[derive(Clone, PartialEq)]
+pub struct SafetyViolation<V> {
+    pub round: usize,
+    pub node: usize,
+    pub violation: Violation<V>,
+}
+
+impl<V: Debug> Debug for SafetyViolation<V> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "node {} violates {:?} in round {}",
+            self.node, self.violation, self.round
+        )
+    }
+}
+
+impl<V: Debug> Traceable for SafetyViolation<V> {
+    fn action(&self) -> &'static str {
+        "violation"
+    }
+
+    fn fields(&self) -> Vec<(&'static str, Field)> {
+        let (property, slot) = match &self.violation {
+            Violation::Agreement { slot, .. } => ("agreement", slot),
+            Violation::Validity { slot, .. } => ("validity", slot),
+            Violation::Stability { slot, .. } => ("stability", slot),
+        };
+        vec![
+            ("node", self.node.into()),
+            ("property", property.into()),
+            ("slot", (*slot).into()),
+            ("violation", Field::debug(&self.violation)),
+        ]
+    }
+}
+
+/// Remembers every decision seen so far to check new ones against them.
+pub struct SafetyChecker<V> {
+    inputs: Vec<Command<V>>,
+    decided: HashMap<Slot, (usize, Command<V>)>,
+    decisions: HashMap<(usize, Slot), Command<V>>,
+}
+
+impl<V> Debug for SafetyChecker<V> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Checker")
+    }
+}
+
+impl<V> Actor for SafetyChecker<V> {
+    fn actor_kind(&self) -> &'static str {
+        "Checker"
+    }
+
+    fn actor_id(&self) -> Option<usize> {
+        None
+    }
+}
+
+impl<V: Value> SafetyChecker<V> {
+    /// `inputs` are all values that may be decided.
+    pub fn new(inputs: Vec<Command<V>>) -> Self {
+        SafetyChecker {
+            inputs,
+            decided: HashMap::new(),
+            decisions: HashMap::new(),
+        }
+    }
+
+    /// Checks the `(slot, value)` decisions `node` holds in `round`.
+    pub fn observe(
+        &mut self,
+        round: usize,
+        node: usize,
+        decisions: &[(Slot, Command<V>)],
+    ) -> Result<(), SafetyViolation<V>> {
+        for (slot, value) in decisions.iter().cloned() {
+            let violation = |violation| SafetyViolation {
+                round,
+                node,
+                violation,
+            };
+            if let Some(old) = self.decisions.get(&(node, slot)) {
+                if *old != value {
+                    let (old, new) = (old.clone(), value);
+                    return Err(violation(Violation::Stability { slot, old, new }));
+                }
+                continue;
+            }
+            if !self.inputs.contains(&value) {
+                return Err(violation(Violation::Validity { slot, value }));
+            }
+            if let Some((_, other)) = self.decided.get(&slot) {
+                if *other != value {
+                    let other = other.clone();
+                    return Err(violation(Violation::Agreement { slot, value, other }));
+                }
+            }
+            self.decided.entry(slot).or_insert((node, value.clone()));
+            self.decisions.insert((node, slot), value);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{SafetyChecker, Violation};
+    use crate::paxos::Command;
+
+    #[test]
+    fn checker_reports_first_violation() {
+        let inputs = vec![Command::new(0, 1, 1), Command::new(0, 2, 2)];
+        let mut checker = SafetyChecker::new(inputs);
+        assert!(checker.observe(0, 0, &[(0, Command::new(0, 1, 1))]).is_ok());
+        assert!(checker.observe(1, 0, &[(0, Command::new(0, 1, 1))]).is_ok());
+
+        let err = checker
+            .observe(2, 1, &[(0, Command::new(0, 2, 2))])
+            .unwrap_err();
+        assert_eq!((err.round, err.node), (2, 1));
+        assert!(matches!(
+            err.violation,
+            Violation::Agreement { slot: 0, .. }
+        ));
+
+        let err = checker
+            .observe(3, 0, &[(0, Command::new(0, 2, 2))])
+            .unwrap_err();
+        assert!(matches!(
+            err.violation,
+            Violation::Stability { slot: 0, .. }
+        ));
+
+        let err = checker
+            .observe(4, 2, &[(1, Command::new(0, 3, 3))])
+            .unwrap_err();
+        assert_eq!(
+            err.violation,
+            Violation::Validity {
+                slot: 1,
+                value: Command::new(0, 3, 3)
+            }
+        );
+    }
+}
@@ -1,16 +1,21 @@
 use super::*;
-pub struct Client {
+use std::collections::{HashSet, VecDeque};
+pub struct Client<V> {
     id: usize,
     wait_duration: usize,
-    link: LinkInterface,
+    link: LinkInterface<V>,
     servers: ServerList,
-    command: Command,
+    command: Command<V>,
+    pending: VecDeque<Command<V>>, // own commands not yet in the log
+    executed: Vec<Command<V>>,     // the log as far as this client executed it
+    slot: Slot,
+    multi_decree: bool,
     cur_ticket: Ticket,
-    state: usize, // 0 = ask for ticket, 1 = proposing, 2 = success
-    inbox: Vec<Packet<Message>>,
+    state: usize, // 0 = ask for ticket, 1 = proposing, 2 = success, 3 = done
+    inbox: Vec<Packet<Message<V>>>,
 }
 
-impl Node for Client {
+impl<V: Value> Node<V> for Client<V> {
     fn exec(&mut self, logger: &mut Logger) {
         logger.log_actor(self);
         self.get_mail();
@@ -18,61 +23,58 @@ impl Node for Client {
         match self.state {
             0 => {
                 self.inbox.clear();
-                self.cur_ticket += 1;
-                logger.log_action(&Action::Store(
+                self.cur_ticket.round += 1;
+                logger.log_action(&Action::<V>::Store(
                     String::from("t"),
                     format!("{}", self.cur_ticket),
                 ));
                 for server_id in self.servers.borrow_mut().iter() {
-                    let message = Message::Ask(self.cur_ticket);
-                    self.send_message(*server_id, message);
-                    logger.log_action(&Action::Send(*server_id, message));
+                    let message = Message::Ask(self.slot, self.cur_ticket);
+                    self.send_message(*server_id, message, logger);
                 }
-                logger.log_action(&Action::StateChange(0, 1));
+                logger.log_action(&Action::<V>::StateChange(0, 1));
                 self.state = 1;
                 self.reset_wait();
             }
             1 => {
+                let slot = self.slot;
                 self.inbox
-                    .retain(|x| matches!(x.content, Message::Ok(_, _)));
-                self.inbox
-                    .iter()
-                    .for_each(|m| logger.log_action(&Action::Receive(m.sender, m.content)));
-                logger.log_action(&Action::Check(
+                    .retain(|x| matches!(x.content, Message::Ok(s, _, _) if s == slot));
+                self.dedup_senders();
+                self.inbox.iter().for_each(|m| self.receive(m, logger));
+                logger.log_action(&Action::<V>::Check(
                     String::from("#received ok's > #nr servers / 2"),
                     format!("{} > {}", self.inbox.len(), server_count),
                     self.inbox.len() > server_count / 2,
                 ));
 
                 if self.inbox.len() > server_count / 2 {
-                    let mut max = 0;
+                    let mut max = Ticket::default();
                     for p in self.inbox.iter() {
-                        match p.content {
-                            Message::Ok(t_tstore, c) => {
-                                if t_tstore > max && t_tstore > 0 {
-                                    max = t_tstore;
-                                    logger.log_action(&Action::Store(
-                                        String::from("c"),
-                                        format!("{:?}", c),
-                                    ));
-                                    self.command = c;
-                                }
+                        if let Message::Ok(_, t_tstore, c) = &p.content {
+                            let t_tstore = *t_tstore;
+                            if t_tstore > max {
+                                max = t_tstore;
+                                logger.log_action(&Action::<V>::Store(
+                                    String::from("c"),
+                                    format!("{:?}", c),
+                                ));
+                                self.command = c.clone();
                             }
-                            _ => (),
                         }
                     }
 
                     for p in self.inbox.iter() {
-                        let message = Message::Propose(self.cur_ticket, self.command);
-                        logger.log_action(&Action::Send(p.sender, message));
-                        self.send_message(p.sender, message)
+                        let message =
+                            Message::Propose(self.slot, self.cur_ticket, self.command.clone());
+                        self.send_message(p.sender, message, logger)
                     }
-                    logger.log_action(&Action::StateChange(1, 2));
+                    logger.log_action(&Action::<V>::StateChange(1, 2));
                     self.state = 2;
                     self.reset_wait();
                 } else {
                     if self.wait_duration == 0 {
-                        logger.log_action(&Action::StateChange(1, 0));
+                        logger.log_action(&Action::<V>::StateChange(1, 0));
                         self.state = 0;
                     } else {
                         self.wait_duration -= 1;
@@ -80,12 +82,13 @@ impl Node for Client {
                 }
             }
             2 => {
-                self.inbox.retain(|x| matches!(x.content, Message::Success));
+                let slot = self.slot;
                 self.inbox
-                    .iter()
-                    .for_each(|m| logger.log_action(&Action::Receive(m.sender, m.content)));
+                    .retain(|x| matches!(x.content, Message::Success(s, _) if s == slot));
+                self.dedup_senders();
+                self.inbox.iter().for_each(|m| self.receive(m, logger));
 
-                logger.log_action(&Action::Check(
+                logger.log_action(&Action::<V>::Check(
                     String::from("#received successes' > #nr servers / 2"),
                     format!("{} > {}", self.inbox.len(), server_count),
                     self.inbox.len() > server_count / 2,
@@ -93,71 +96,168 @@ impl Node for Client {
 
                 if self.inbox.len() > server_count / 2 {
                     for server in self.servers.borrow_mut().iter() {
-                        let message = Message::Execute(self.command);
-                        logger.log_action(&Action::Send(*server, message));
-                        self.send_message(*server, message)
+                        let message = Message::Execute(self.slot, self.command.clone());
+                        self.send_message(*server, message, logger)
+                    }
+                    self.executed.push(self.command.clone());
+                    if self.pending.front().and_then(Command::id) == self.command.id() {
+                        self.pending.pop_front();
+                    }
+                    match self.pending.front() {
+                        Some(command) if self.multi_decree => {
+                            self.slot += 1;
+                            logger.log_action(&Action::<V>::Store(
+                                String::from("slot"),
+                                format!("{}", self.slot),
+                            ));
+                            self.command = command.clone();
+                            logger.log_action(&Action::<V>::StateChange(2, 0));
+                            self.state = 0;
+                        }
+                        _ => {
+                            logger.log_action(&Action::<V>::StateChange(2, 3));
+                            self.state = 3;
+                            self.reset_wait();
+                        }
                     }
-                    logger.log_action(&Action::StateChange(2, 3));
-                    self.state = 3;
-                    self.reset_wait();
                 } else {
                     if self.wait_duration == 0 {
-                        logger.log_action(&Action::StateChange(2, 0));
+                        logger.log_action(&Action::<V>::StateChange(2, 0));
                         self.state = 0;
                     } else {
                         self.wait_duration -= 1;
                     }
                 }
             }
-            3 => self.inbox.clear(),
+            3 => {
+                self.inbox.clear();
+                // Repeated for servers that crashed or lost packets.
+                if self.wait_duration == 0 {
+                    for server in self.servers.borrow_mut().iter() {
+                        for (slot, command) in self.executed.iter().enumerate() {
+                            let message = Message::Execute(slot, command.clone());
+                            self.send_message(*server, message, logger)
+                        }
+                    }
+                    self.reset_wait();
+                } else {
+                    self.wait_duration -= 1;
+                }
+            }
             _ => panic!("Unexptected Client state"),
         }
     }
 
-    fn get_command(&self) -> Command {
-        self.command
+    fn get_command(&self) -> Command<V> {
+        self.command.clone()
     }
 
     fn has_decided(&self) -> bool {
-        true
+        !self.multi_decree || self.state == 3
+    }
+
+    fn committed(&self) -> Vec<Command<V>> {
+        self.executed.clone()
+    }
+
+    fn decisions(&self) -> Vec<(Slot, Command<V>)> {
+        Vec::new()
+    }
+
+    fn inputs(&self) -> Vec<Command<V>> {
+        self.pending.iter().cloned().collect()
     }
 
     fn get_type(&self) -> usize {
         CLIENT
     }
+
+    // The ticket counter survives, so old tickets are never reused.
+    fn crash(&mut self) {
+        self.link.borrow_mut().empty_buffer();
+        self.inbox.clear();
+        self.state = 0;
+        self.wait_duration = 0;
+    }
+
+    fn recover(&mut self) {}
+
+    // Waiting clients only count down until they time out.
+    fn idle_rounds(&self) -> usize {
+        match self.state {
+            0 => 0,
+            _ => self.wait_duration,
+        }
+    }
+
+    fn skip_rounds(&mut self, rounds: usize) {
+        self.wait_duration -= rounds;
+    }
+
+    fn state(&self) -> Vec<(String, String)> {
+        vec![
+            (String::from("state"), self.state.to_string()),
+            (String::from("t"), self.cur_ticket.to_string()),
+            (String::from("c"), format!("{:?}", self.command)),
+            (String::from("slot"), self.slot.to_string()),
+            (String::from("wait"), self.wait_duration.to_string()),
+        ]
+    }
 }
 
-impl Debug for Client {
+impl<V> Debug for Client<V> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(f, "Client #{}", self.id)
     }
 }
 
-impl Client {
-    pub fn new(id: usize, link: LinkInterface, servers: ServerList, command: Command) -> Self {
+impl<V> Actor for Client<V> {
+    fn actor_kind(&self) -> &'static str {
+        "Client"
+    }
+
+    fn actor_id(&self) -> Option<usize> {
+        Some(self.id)
+    }
+}
+
+impl<V: Value> Client<V> {
+    /// Single-decree client, done once slot 0 is decided.
+    pub fn new(
+        id: usize,
+        link: LinkInterface<V>,
+        servers: ServerList,
+        command: Command<V>,
+    ) -> Self {
         Client {
             wait_duration: 0,
             id,
             state: 0,
-            cur_ticket: 0,
-            command,
+            cur_ticket: Ticket::new(0, id),
+            command: command.clone(),
+            pending: VecDeque::from([command]),
+            executed: Vec::new(),
+            slot: 0,
+            multi_decree: false,
             link,
             servers,
             inbox: Vec::new(),
         }
     }
 
-    pub fn new_rand(id: usize, link: LinkInterface, servers: ServerList, rng: &mut StdRng) -> Self {
-        let random_command = rng.gen::<bool>();
+    /// Multi-decree client, done once all of `commands` are in the log.
+    pub fn new_sequence(
+        id: usize,
+        link: LinkInterface<V>,
+        servers: ServerList,
+        commands: Vec<Command<V>>,
+    ) -> Self {
+        assert!(!commands.is_empty(), "Client needs at least one command");
         Client {
-            wait_duration: 0,
-            id,
-            state: 0,
-            cur_ticket: 0,
-            command: Command::Defined(random_command),
-            link,
-            servers,
-            inbox: Vec::new(),
+            command: commands[0].clone(),
+            pending: VecDeque::from(commands),
+            multi_decree: true,
+            ..Self::new(id, link, servers, Command::Undefined)
         }
     }
 
@@ -165,8 +265,20 @@ impl Client {
         self.inbox.extend(self.link.borrow_mut().empty_buffer());
     }
 
-    fn send_message(&self, receiver: usize, message: Message) {
-        self.link.borrow_mut().enqueue(receiver, message)
+    fn receive(&self, packet: &Packet<Message<V>>, logger: &mut Logger) {
+        self.link.borrow_mut().receive(packet);
+        logger.log_action(&Action::Receive(packet.clone()));
+    }
+
+    fn send_message(&self, receiver: usize, message: Message<V>, logger: &mut Logger) {
+        let id = self.link.borrow_mut().enqueue(receiver, message.clone());
+        logger.log_action(&Action::Send(receiver, id, message));
+    }
+
+    // Duplicated replies must not count twice towards a majority.
+    fn dedup_senders(&mut self) {
+        let mut seen = HashSet::new();
+        self.inbox.retain(|p| seen.insert(p.sender));
     }
 
     fn reset_wait(&mut self) {
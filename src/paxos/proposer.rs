@@ -0,0 +1,394 @@
+use super::*;
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
+
+/// Proposer of Multi-Paxos, as leader it only runs the second phase.
+pub struct Proposer<V> {
+    id: usize,
+    link: LinkInterface<V>,
+    servers: ServerList,
+    ticket: Ticket,
+    t_seen: Ticket,
+    leader: bool,
+    preparing: bool,
+    promises: HashSet<usize>,
+    accepted: BTreeMap<Slot, (Ticket, Command<V>, bool)>,
+    requests: VecDeque<Request<V>>,
+    proposals: BTreeMap<Slot, Proposal<V>>,
+    decided: BTreeMap<Slot, Command<V>>,
+    slots: HashMap<CommandId, Slot>, // where each decided command is
+    next_slot: Slot,
+    wait_duration: usize,
+}
+
+/// A command and the client waiting for it.
+#[derive(Clone)]
+struct Request<V> {
+    client: usize,
+    seq: usize,
+    command: Command<V>,
+}
+
+struct Proposal<V> {
+    command: Command<V>,
+    request: Option<Request<V>>,
+    successes: HashSet<usize>,
+}
+
+impl<V: Value> Node<V> for Proposer<V> {
+    fn exec(&mut self, logger: &mut Logger) {
+        logger.log_actor(self);
+        let inbox = self.link.borrow_mut().empty_buffer();
+        let server_count = self.servers.borrow().len();
+        let idle = self.proposals.is_empty() && !self.preparing;
+        for packet in inbox {
+            self.link.borrow_mut().receive(&packet);
+            logger.log_action(&Action::Receive(packet.clone()));
+            match packet.content {
+                Message::Request(seq, command) => {
+                    let request = Request {
+                        client: packet.sender,
+                        seq,
+                        command,
+                    };
+                    self.receive_request(request, logger);
+                }
+                Message::Promise(ticket, accepted)
+                    if self.preparing
+                        && ticket == self.ticket
+                        && !self.promises.contains(&packet.sender) =>
+                {
+                    self.promises.insert(packet.sender);
+                    for (slot, t_store, command, decided) in accepted {
+                        self.t_seen = self.t_seen.max(t_store);
+                        let best = self.accepted.entry(slot).or_insert((
+                            Ticket::default(),
+                            Command::Undefined,
+                            false,
+                        ));
+                        if decided || (!best.2 && t_store >= best.0) {
+                            *best = (t_store, command, decided);
+                        }
+                    }
+                }
+                Message::Success(slot, ticket) if ticket == self.ticket => {
+                    if let Some(proposal) = self.proposals.get_mut(&slot) {
+                        proposal.successes.insert(packet.sender);
+                    }
+                }
+                // Replies to outdated tickets.
+                _ => {}
+            }
+        }
+
+        if self.preparing {
+            logger.log_action(&Action::<V>::Check(
+                String::from("#received promises > #nr servers / 2"),
+                format!("{} > {}", self.promises.len(), server_count),
+                self.promises.len() > server_count / 2,
+            ));
+            if self.promises.len() > server_count / 2 {
+                self.lead(logger);
+            }
+        }
+
+        let committed: Vec<Slot> = self
+            .proposals
+            .iter()
+            .filter(|(_, p)| p.successes.len() > server_count / 2)
+            .map(|(slot, _)| *slot)
+            .collect();
+        for slot in committed {
+            let proposal = self.proposals.remove(&slot).unwrap();
+            self.commit(slot, proposal.command, proposal.request, logger);
+            self.reset_wait();
+        }
+
+        if self.leader {
+            if idle && !self.requests.is_empty() {
+                self.reset_wait();
+            }
+            while let Some(request) = self.requests.pop_front() {
+                let id = request.command.id();
+                if let Some(slot) = id.and_then(|id| self.slots.get(&id)) {
+                    self.reply(&request, *slot, logger);
+                    continue;
+                }
+                if let Some(proposal) = self.proposals.values_mut().find(|p| p.command.id() == id) {
+                    proposal.request.get_or_insert(request);
+                    continue;
+                }
+                let slot = self.next_slot;
+                self.next_slot += 1;
+                self.propose(slot, request.command.clone(), Some(request), logger);
+            }
+        } else if !self.preparing && !self.requests.is_empty() {
+            self.prepare(logger);
+        }
+
+        if self.leader || self.preparing {
+            if self.wait_duration > 0 {
+                self.wait_duration -= 1;
+            } else if self.preparing || !self.proposals.is_empty() {
+                // Someone else took over the servers, bump the ticket.
+                self.prepare(logger);
+            } else {
+                for (slot, command) in self.decided.clone() {
+                    self.broadcast(Message::Execute(slot, command), logger);
+                }
+                self.reset_wait();
+            }
+        }
+    }
+
+    fn get_command(&self) -> Command<V> {
+        self.decided.get(&0).cloned().unwrap_or(Command::Undefined)
+    }
+
+    fn has_decided(&self) -> bool {
+        true
+    }
+
+    fn committed(&self) -> Vec<Command<V>> {
+        (0..)
+            .map_while(|slot| self.decided.get(&slot).cloned())
+            .collect()
+    }
+
+    fn decisions(&self) -> Vec<(Slot, Command<V>)> {
+        Vec::new()
+    }
+
+    // Gaps in the log are filled with no-ops.
+    fn inputs(&self) -> Vec<Command<V>> {
+        vec![Command::Undefined]
+    }
+
+    fn get_type(&self) -> usize {
+        PROPOSER
+    }
+
+    // Only the ticket is kept, so a restarted proposer never reuses one.
+    fn crash(&mut self) {
+        self.link.borrow_mut().empty_buffer();
+        self.leader = false;
+        self.preparing = false;
+        self.promises.clear();
+        self.accepted.clear();
+        self.requests.clear();
+        self.proposals.clear();
+        self.decided.clear();
+        self.slots.clear();
+        self.next_slot = 0;
+        self.wait_duration = 0;
+    }
+
+    fn recover(&mut self) {}
+
+    fn idle_rounds(&self) -> usize {
+        match (self.leader || self.preparing, self.requests.is_empty()) {
+            (_, false) => 0,
+            (true, true) => self.wait_duration,
+            (false, true) => usize::MAX,
+        }
+    }
+
+    fn skip_rounds(&mut self, rounds: usize) {
+        if self.leader || self.preparing {
+            self.wait_duration -= rounds;
+        }
+    }
+
+    fn state(&self) -> Vec<(String, String)> {
+        vec![
+            (String::from("t"), self.ticket.to_string()),
+            (String::from("leader"), self.leader.to_string()),
+            (String::from("requests"), self.requests.len().to_string()),
+            (String::from("next_slot"), self.next_slot.to_string()),
+            (String::from("wait"), self.wait_duration.to_string()),
+        ]
+    }
+}
+
+impl<V> Debug for Proposer<V> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Proposer #{}", self.id)
+    }
+}
+
+impl<V> Actor for Proposer<V> {
+    fn actor_kind(&self) -> &'static str {
+        "Proposer"
+    }
+
+    fn actor_id(&self) -> Option<usize> {
+        Some(self.id)
+    }
+}
+
+impl<V: Value> Proposer<V> {
+    pub fn new(id: usize, link: LinkInterface<V>, servers: ServerList) -> Self {
+        Proposer {
+            id,
+            link,
+            servers,
+            ticket: Ticket::new(0, id),
+            t_seen: Ticket::default(),
+            leader: false,
+            preparing: false,
+            promises: HashSet::new(),
+            accepted: BTreeMap::new(),
+            requests: VecDeque::new(),
+            proposals: BTreeMap::new(),
+            decided: BTreeMap::new(),
+            slots: HashMap::new(),
+            next_slot: 0,
+            wait_duration: 0,
+        }
+    }
+
+    fn receive_request(&mut self, request: Request<V>, logger: &mut Logger) {
+        // A retry of a decided request, the reply got lost.
+        if let Some(slot) = request.command.id().and_then(|id| self.slots.get(&id)) {
+            self.reply(&request, *slot, logger);
+            return;
+        }
+        let waiting = |r: &Request<V>| r.client == request.client && r.seq == request.seq;
+        let in_flight = self
+            .proposals
+            .values()
+            .any(|p| p.request.as_ref().is_some_and(waiting));
+        if in_flight || self.requests.iter().any(waiting) {
+            return;
+        }
+        logger.log_action(&Action::<V>::Store(
+            String::from("requests"),
+            format!("{} entries", self.requests.len() + 1),
+        ));
+        self.requests.push_back(request);
+    }
+
+    /// Runs the first phase for all slots with a fresh ticket.
+    fn prepare(&mut self, logger: &mut Logger) {
+        self.leader = false;
+        self.preparing = true;
+        let round = self.ticket.round.max(self.t_seen.round) + 1;
+        self.ticket = Ticket::new(round, self.id);
+        logger.log_action(&Action::<V>::Store(
+            String::from("t"),
+            format!("{}", self.ticket),
+        ));
+        self.promises.clear();
+        self.accepted.clear();
+        self.broadcast(Message::Prepare(self.ticket), logger);
+        self.reset_wait();
+    }
+
+    /// Proposes accepted values again and fills gaps with `Undefined`.
+    fn lead(&mut self, logger: &mut Logger) {
+        logger.log_action(&Action::<V>::Store(
+            String::from("leader"),
+            format!("{}", self.id),
+        ));
+        self.leader = true;
+        self.preparing = false;
+        self.reset_wait();
+
+        let mut own = std::mem::take(&mut self.proposals);
+        let end = [
+            self.accepted.keys().last(),
+            own.keys().last(),
+            self.decided.keys().last(),
+        ]
+        .into_iter()
+        .flatten()
+        .map(|slot| slot + 1)
+        .fold(self.next_slot, usize::max);
+
+        for slot in 0..end {
+            if self.decided.contains_key(&slot) {
+                continue;
+            }
+            let mine = own.remove(&slot);
+            let (command, decided) = match self.accepted.get(&slot) {
+                Some((_, command, decided)) => (command.clone(), *decided),
+                None => (
+                    mine.as_ref()
+                        .map_or(Command::Undefined, |p| p.command.clone()),
+                    false,
+                ),
+            };
+            let request = match mine {
+                Some(p) if p.command == command => p.request,
+                Some(p) => {
+                    // Lost the slot, the command needs a new one.
+                    self.requests.extend(p.request);
+                    None
+                }
+                None => None,
+            };
+            if decided {
+                self.commit(slot, command, request, logger);
+            } else {
+                self.propose(slot, command, request, logger);
+            }
+        }
+        self.next_slot = end;
+        self.accepted.clear();
+    }
+
+    fn propose(
+        &mut self,
+        slot: Slot,
+        command: Command<V>,
+        request: Option<Request<V>>,
+        logger: &mut Logger,
+    ) {
+        self.broadcast(Message::Propose(slot, self.ticket, command.clone()), logger);
+        self.proposals.insert(
+            slot,
+            Proposal {
+                command,
+                request,
+                successes: HashSet::new(),
+            },
+        );
+    }
+
+    fn commit(
+        &mut self,
+        slot: Slot,
+        command: Command<V>,
+        request: Option<Request<V>>,
+        logger: &mut Logger,
+    ) {
+        logger.log_action(&Action::Decide(slot, command.clone()));
+        if let Some(id) = command.id() {
+            self.slots.insert(id, slot);
+        }
+        self.decided.insert(slot, command.clone());
+        self.broadcast(Message::Execute(slot, command), logger);
+        if let Some(request) = request {
+            self.reply(&request, slot, logger);
+        }
+    }
+
+    fn reply(&self, request: &Request<V>, slot: Slot, logger: &mut Logger) {
+        let message = Message::Reply(request.seq, slot);
+        self.send_message(request.client, message, logger);
+    }
+
+    fn broadcast(&self, message: Message<V>, logger: &mut Logger) {
+        for server in self.servers.borrow().iter() {
+            self.send_message(*server, message.clone(), logger);
+        }
+    }
+
+    fn send_message(&self, receiver: usize, message: Message<V>, logger: &mut Logger) {
+        let id = self.link.borrow_mut().enqueue(receiver, message.clone());
+        logger.log_action(&Action::Send(receiver, id, message));
+    }
+
+    fn reset_wait(&mut self) {
+        self.wait_duration = WAIT_DURATION;
+    }
+}
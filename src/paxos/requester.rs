@@ -0,0 +1,174 @@
+use super::*;
+use std::collections::VecDeque;
+
+/// Client of Multi-Paxos, it moves on to the next proposer on a timeout.
+pub struct Requester<V> {
+    id: usize,
+    wait_duration: usize,
+    link: LinkInterface<V>,
+    proposers: Vec<usize>,
+    leader: usize,
+    commands: VecDeque<Command<V>>,
+    command: Command<V>,
+    seq: usize,
+    state: usize, // 0 = send request, 1 = waiting for reply, 2 = done
+}
+
+impl<V: Value> Node<V> for Requester<V> {
+    fn exec(&mut self, logger: &mut Logger) {
+        logger.log_actor(self);
+        let inbox = self.link.borrow_mut().empty_buffer();
+        for packet in inbox {
+            if let Message::Reply(seq, _) = packet.content {
+                if seq != self.seq || self.state != 1 {
+                    continue;
+                }
+                self.link.borrow_mut().receive(&packet);
+                logger.log_action(&Action::Receive(packet.clone()));
+                self.leader = self
+                    .proposers
+                    .iter()
+                    .position(|&p| p == packet.sender)
+                    .unwrap();
+                self.commands.pop_front();
+                self.seq += 1;
+                match self.commands.front() {
+                    Some(command) => {
+                        self.command = command.clone();
+                        logger.log_action(&Action::<V>::StateChange(1, 0));
+                        self.state = 0;
+                    }
+                    None => {
+                        logger.log_action(&Action::<V>::StateChange(1, 2));
+                        self.state = 2;
+                    }
+                }
+            }
+        }
+
+        match self.state {
+            0 => {
+                let message = Message::Request(self.seq, self.command.clone());
+                let proposer = self.proposers[self.leader];
+                let id = self.link.borrow_mut().enqueue(proposer, message.clone());
+                logger.log_action(&Action::Send(proposer, id, message));
+                logger.log_action(&Action::<V>::StateChange(0, 1));
+                self.state = 1;
+                // Proposers get the chance to retry before being replaced.
+                self.wait_duration = 2 * WAIT_DURATION;
+            }
+            1 => {
+                if self.wait_duration == 0 {
+                    self.leader = (self.leader + 1) % self.proposers.len();
+                    logger.log_action(&Action::<V>::Store(
+                        String::from("leader"),
+                        format!("{}", self.proposers[self.leader]),
+                    ));
+                    logger.log_action(&Action::<V>::StateChange(1, 0));
+                    self.state = 0;
+                } else {
+                    self.wait_duration -= 1;
+                }
+            }
+            2 => {}
+            _ => panic!("Unexptected Requester state"),
+        }
+    }
+
+    fn get_command(&self) -> Command<V> {
+        self.command.clone()
+    }
+
+    fn has_decided(&self) -> bool {
+        self.state == 2
+    }
+
+    fn committed(&self) -> Vec<Command<V>> {
+        Vec::new()
+    }
+
+    fn decisions(&self) -> Vec<(Slot, Command<V>)> {
+        Vec::new()
+    }
+
+    fn inputs(&self) -> Vec<Command<V>> {
+        self.commands.iter().cloned().collect()
+    }
+
+    fn get_type(&self) -> usize {
+        CLIENT
+    }
+
+    fn crash(&mut self) {
+        self.link.borrow_mut().empty_buffer();
+        if self.state == 1 {
+            self.state = 0;
+        }
+        self.wait_duration = 0;
+    }
+
+    fn recover(&mut self) {}
+
+    fn idle_rounds(&self) -> usize {
+        match self.state {
+            0 => 0,
+            1 => self.wait_duration,
+            _ => usize::MAX,
+        }
+    }
+
+    fn skip_rounds(&mut self, rounds: usize) {
+        if self.state == 1 {
+            self.wait_duration -= rounds;
+        }
+    }
+
+    fn state(&self) -> Vec<(String, String)> {
+        vec![
+            (String::from("state"), self.state.to_string()),
+            (String::from("leader"), self.leader.to_string()),
+            (String::from("seq"), self.seq.to_string()),
+            (String::from("c"), format!("{:?}", self.command)),
+            (String::from("wait"), self.wait_duration.to_string()),
+        ]
+    }
+}
+
+impl<V> Debug for Requester<V> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Client #{}", self.id)
+    }
+}
+
+impl<V> Actor for Requester<V> {
+    fn actor_kind(&self) -> &'static str {
+        "Client"
+    }
+
+    fn actor_id(&self) -> Option<usize> {
+        Some(self.id)
+    }
+}
+
+impl<V: Value> Requester<V> {
+    pub fn new(
+        id: usize,
+        link: LinkInterface<V>,
+        proposers: Vec<usize>,
+        commands: Vec<Command<V>>,
+    ) -> Self {
+        assert!(!commands.is_empty(), "Client needs at least one command");
+        assert!(!proposers.is_empty(), "Client needs at least one proposer");
+        Requester {
+            id,
+            wait_duration: 0,
+            link,
+            proposers,
+            leader: 0,
+            command: commands[0].clone(),
+            commands: VecDeque::from(commands),
+            seq: 0,
+            state: 0,
+        }
+    }
+}
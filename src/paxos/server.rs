@@ -1,103 +1,265 @@
 use super::*;
-pub struct Server {
+pub struct Server<V> {
     id: usize,
-    link: LinkInterface,
+    link: LinkInterface<V>,
+    log: Vec<Entry<V>>,
+    promised: Ticket,
+    storage: Storage<V>,
+}
+
+/// The part of a server's state that survives a crash. Decisions are not
+/// stored, clients keep repeating them.
+struct Storage<V> {
+    log: Vec<Stored<V>>,
+    promised: Ticket,
+}
+
+/// The durable fields of a slot.
+struct Stored<V> {
     t_max: Ticket,
-    command: Command,
     t_store: Ticket,
+    command: Command<V>,
+}
+
+/// A slot of the log, an instance of single-decree Paxos.
+#[derive(Clone)]
+struct Entry<V> {
+    t_max: Ticket,
+    t_store: Ticket,
+    command: Command<V>,
     decided: bool,
 }
 
-impl Node for Server {
+impl<V> Default for Entry<V> {
+    fn default() -> Self {
+        Entry {
+            t_max: Ticket::default(),
+            t_store: Ticket::default(),
+            command: Command::Undefined,
+            decided: false,
+        }
+    }
+}
+
+impl<V: Value> Node<V> for Server<V> {
     fn exec(&mut self, logger: &mut Logger) {
         logger.log_actor(self);
         let inbox = self.link.borrow_mut().empty_buffer();
         for packet in inbox {
-            logger.log_action(&Action::Receive(packet.sender, packet.content));
+            self.link.borrow_mut().receive(&packet);
+            logger.log_action(&Action::Receive(packet.clone()));
             match packet.content {
-                Message::Ask(ticket) => {
-                    logger.log_action(&Action::Check(
+                Message::Ask(slot, ticket) => {
+                    let t_max = self.t_max(slot);
+                    logger.log_action(&Action::<V>::Check(
                         String::from("received ticket > t_max"),
-                        format!("{} > {}", ticket, self.t_max),
-                        ticket > self.t_max,
+                        format!("{} > {}", ticket, t_max),
+                        ticket > t_max,
+                    ));
+
+                    if ticket > t_max {
+                        let entry = self.entry(slot);
+                        entry.t_max = ticket;
+                        let message = Message::Ok(slot, entry.t_store, entry.command.clone());
+                        logger.log_action(&Action::<V>::Store(
+                            format!("t_max[{slot}]"),
+                            format!("{}", ticket),
+                        ));
+                        self.send_message(packet.sender, message, logger);
+                    }
+                }
+                Message::Prepare(ticket) => {
+                    let t_max = (0..self.log.len()).map(|s| self.t_max(s)).max();
+                    let t_max = t_max.unwrap_or(self.promised);
+                    logger.log_action(&Action::<V>::Check(
+                        String::from("received ticket > t_max of all slots"),
+                        format!("{} > {}", ticket, t_max),
+                        ticket > t_max,
                     ));
 
-                    if ticket > self.t_max {
-                        self.t_max = ticket;
-                        logger.log_action(&Action::Store(
-                            String::from("t_max"),
+                    if ticket > t_max {
+                        self.promised = ticket;
+                        logger.log_action(&Action::<V>::Store(
+                            String::from("t_max[*]"),
                             format!("{}", ticket),
                         ));
-                        let message = Message::Ok(self.t_store, self.command);
-                        logger.log_action(&Action::Send(packet.sender, message));
-                        self.send_message(packet.sender, message);
+                        let accepted = self
+                            .log
+                            .iter()
+                            .enumerate()
+                            .filter(|(_, e)| e.t_store > Ticket::default() || e.decided)
+                            .map(|(slot, e)| (slot, e.t_store, e.command.clone(), e.decided))
+                            .collect();
+                        let message = Message::Promise(ticket, accepted);
+                        self.send_message(packet.sender, message, logger);
                     }
                 }
-                Message::Propose(ticket, command) => {
-                    logger.log_action(&Action::Check(
+                Message::Propose(slot, ticket, command) => {
+                    let t_max = self.t_max(slot);
+                    logger.log_action(&Action::<V>::Check(
                         String::from("received ticket == t_max"),
-                        format!("{} == {}", ticket, self.t_max),
-                        ticket == self.t_max,
+                        format!("{} == {}", ticket, t_max),
+                        ticket == t_max,
                     ));
 
-                    if self.t_max == ticket {
-                        logger.log_action(&Action::Store(
-                            String::from("C"),
+                    if t_max == ticket {
+                        let entry = self.entry(slot);
+                        logger.log_action(&Action::<V>::Store(
+                            format!("C[{slot}]"),
                             format!("{:?}", command),
                         ));
-                        self.command = command;
+                        entry.command = command;
 
-                        logger.log_action(&Action::Store(
-                            String::from("t_store"),
+                        logger.log_action(&Action::<V>::Store(
+                            format!("t_store[{slot}]"),
                             format!("{}", ticket),
                         ));
-                        self.t_store = ticket;
+                        entry.t_store = ticket;
 
-                        let message = Message::Success;
-                        logger.log_action(&Action::Send(packet.sender, message));
-                        self.send_message(packet.sender, Message::Success)
+                        let message = Message::Success(slot, ticket);
+                        self.send_message(packet.sender, message, logger)
                     }
                 }
-                Message::Execute(command) => {
-                    logger.log_action(&Action::Decide(command));
-                    self.command = command;
-                    self.decided = true
+                Message::Execute(slot, command) => {
+                    logger.log_action(&Action::Decide(slot, command.clone()));
+                    let entry = self.entry(slot);
+                    entry.command = command;
+                    entry.decided = true
                 }
                 _ => panic!("Unexpted packet received by server"),
             }
         }
     }
 
-    fn get_command(&self) -> Command {
-        self.command
+    fn get_command(&self) -> Command<V> {
+        self.log
+            .first()
+            .map_or(Command::Undefined, |e| e.command.clone())
     }
 
     fn has_decided(&self) -> bool {
-        self.decided
+        self.log.first().is_some_and(|e| e.decided)
+    }
+
+    fn committed(&self) -> Vec<Command<V>> {
+        self.log
+            .iter()
+            .take_while(|e| e.decided)
+            .map(|e| e.command.clone())
+            .collect()
+    }
+
+    fn decisions(&self) -> Vec<(Slot, Command<V>)> {
+        self.log
+            .iter()
+            .enumerate()
+            .filter(|(_, e)| e.decided)
+            .map(|(slot, e)| (slot, e.command.clone()))
+            .collect()
+    }
+
+    fn inputs(&self) -> Vec<Command<V>> {
+        Vec::new()
     }
 
     fn get_type(&self) -> usize {
         SERVER
     }
+
+    fn crash(&mut self) {
+        self.link.borrow_mut().empty_buffer();
+        self.log.clear();
+        self.promised = Ticket::default();
+    }
+
+    fn idle_rounds(&self) -> usize {
+        usize::MAX
+    }
+
+    fn skip_rounds(&mut self, _rounds: usize) {}
+
+    fn state(&self) -> Vec<(String, String)> {
+        let mut state = vec![(String::from("t_max[*]"), self.promised.to_string())];
+        for (slot, entry) in self.log.iter().enumerate() {
+            state.push((format!("t_max[{slot}]"), entry.t_max.to_string()));
+            state.push((format!("t_store[{slot}]"), entry.t_store.to_string()));
+            state.push((format!("C[{slot}]"), format!("{:?}", entry.command)));
+            state.push((format!("decided[{slot}]"), entry.decided.to_string()));
+        }
+        state
+    }
+
+    fn recover(&mut self) {
+        self.log = self
+            .storage
+            .log
+            .iter()
+            .map(|stored| Entry {
+                t_max: stored.t_max,
+                t_store: stored.t_store,
+                command: stored.command.clone(),
+                decided: false,
+            })
+            .collect();
+        self.promised = self.storage.promised;
+    }
 }
-impl Debug for Server {
+impl<V> Debug for Server<V> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(f, "Server #{}", self.id)
     }
 }
-impl Server {
-    pub fn new(id: usize, link: Rc<RefCell<Link<Message>>>) -> Self {
+impl<V> Actor for Server<V> {
+    fn actor_kind(&self) -> &'static str {
+        "Server"
+    }
+
+    fn actor_id(&self) -> Option<usize> {
+        Some(self.id)
+    }
+}
+impl<V: Value> Server<V> {
+    pub fn new(id: usize, link: LinkInterface<V>) -> Self {
         Server {
             id,
             link,
-            t_max: 0,
-            command: Command::Undefined,
-            t_store: 0,
-            decided: false,
+            log: Vec::new(),
+            promised: Ticket::default(),
+            storage: Storage {
+                log: Vec::new(),
+                promised: Ticket::default(),
+            },
+        }
+    }
+
+    /// Slots the server has not heard of yet start out empty.
+    fn entry(&mut self, slot: Slot) -> &mut Entry<V> {
+        if slot >= self.log.len() {
+            self.log.resize(slot + 1, Entry::default());
         }
+        &mut self.log[slot]
+    }
+
+    /// A `Prepare` promises the ticket for every slot at once.
+    fn t_max(&self, slot: Slot) -> Ticket {
+        let t_max = self.log.get(slot).map_or(Ticket::default(), |e| e.t_max);
+        t_max.max(self.promised)
+    }
+
+    fn persist(&mut self) {
+        let log = self.log.iter().map(|entry| Stored {
+            t_max: entry.t_max,
+            t_store: entry.t_store,
+            command: entry.command.clone(),
+        });
+        self.storage.log = log.collect();
+        self.storage.promised = self.promised;
     }
 
-    fn send_message(&self, receiver: usize, message: Message) {
-        self.link.borrow_mut().enqueue(receiver, message)
+    /// Every answer is preceded by persisting what it promises.
+    fn send_message(&mut self, receiver: usize, message: Message<V>, logger: &mut Logger) {
+        self.persist();
+        let id = self.link.borrow_mut().enqueue(receiver, message.clone());
+        logger.log_action(&Action::Send(receiver, id, message));
     }
 }
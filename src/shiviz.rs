@@ -0,0 +1,103 @@
+use crate::network::VectorClock;
+use crate::trace::{Field, TraceEvent};
+use std::collections::BTreeMap;
+
+/// The expression ShiViz has to parse the log of `to_shiviz` with.
+pub const REGEX: &str = r"(?<event>.*)\n(?<host>\S*) (?<clock>{.*})";
+
+/// Vector clock of every `send` and `receive` in `events`, `None` for all
+/// other actions. Like `Link`, a send ticks the clock of its node and a
+/// receive first merges the clock logged with the packet, so every send
+/// gets the clock its packet carried.
+pub fn clocks(events: &[TraceEvent]) -> Vec<Option<VectorClock>> {
+    let mut nodes: BTreeMap<usize, VectorClock> = BTreeMap::new();
+    events
+        .iter()
+        .map(|event| {
+            let node = event.actor_id?;
+            let clock = nodes.entry(node).or_default();
+            match (event.action, event.field("clock")) {
+                ("send", _) => {}
+                ("receive", Some(Field::Clock(sent))) => clock.merge(sent),
+                _ => return None,
+            }
+            clock.tick(node);
+            Some(clock.clone())
+        })
+        .collect()
+}
+
+/// A log ShiViz can visualize: the parser expression, an empty line and
+/// then every send and receive as a line with its round and text followed
+/// by a line with the host, e.g. `Server0`, and its vector clock.
+pub fn to_shiviz(events: &[TraceEvent]) -> String {
+    let mut hosts: BTreeMap<usize, String> = BTreeMap::new();
+    for event in events {
+        if let Some(id) = event.actor_id {
+            hosts
+                .entry(id)
+                .or_insert_with(|| format!("{}{id}", event.actor));
+        }
+    }
+    let mut log = format!("{REGEX}\n\n");
+    for (event, clock) in events.iter().zip(clocks(events)) {
+        let (Some(id), Some(clock)) = (event.actor_id, clock) else {
+            continue;
+        };
+        log += &format!("round {}: {}\n", event.round, event.text);
+        log += &format!(
+            "{} {}\n",
+            hosts[&id],
+            clock.to_json(|node| hosts[&node].clone())
+        );
+    }
+    log
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::diagram;
+    use crate::sink::NullSink;
+    use crate::trace::TraceFormat;
+    use crate::{paxos, Logger, System};
+
+    #[test]
+    fn receives_happen_after_their_sends() {
+        let mut system: paxos::System = System::new_rand(4, 2, Some(5));
+        let mut logger = Logger::with_sink(Box::new(NullSink), TraceFormat::Text);
+        logger.capture();
+        system.simulate_with(Some(3000), &mut logger);
+        let events = logger.take_events();
+        let clocks = clocks(&events);
+
+        let messages = diagram::messages(&events);
+        assert!(messages.iter().any(|(_, receive)| receive.is_some()));
+        for (send, receive) in messages {
+            let Some(receive) = receive else { continue };
+            let Some(Field::Clock(sent)) = events[receive].field("clock") else {
+                panic!("Receives carry the clock of their packet");
+            };
+            assert_eq!(clocks[send].as_ref(), Some(sent));
+            let (send, receive) = (clocks[send].as_ref(), clocks[receive].as_ref());
+            assert!(send.unwrap().happened_before(receive.unwrap()));
+        }
+        // Events of the same node are ordered by their clocks.
+        let mut last: BTreeMap<usize, &VectorClock> = BTreeMap::new();
+        for (event, clock) in events.iter().zip(&clocks) {
+            if let (Some(id), Some(clock)) = (event.actor_id, clock) {
+                assert!(last.get(&id).is_none_or(|last| last.happened_before(clock)));
+                last.insert(id, clock);
+            }
+        }
+
+        let log = to_shiviz(&events);
+        let mut lines = log.lines();
+        assert_eq!(lines.next(), Some(REGEX));
+        assert_eq!(lines.next(), Some(""));
+        assert!(lines.next().unwrap().starts_with("round 0: "));
+        let clock = lines.next().unwrap();
+        let host = clock.split(' ').next().unwrap();
+        assert_eq!(clock, format!("{host} {{\"{host}\":1}}"));
+    }
+}
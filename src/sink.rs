@@ -0,0 +1,121 @@
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+use std::{cell::RefCell, rc::Rc};
+
+/// Where the `Logger` writes its lines to.
+pub trait LogSink {
+    /// Writes `line` followed by a newline.
+    fn write_line(&mut self, line: &str) -> io::Result<()>;
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+pub struct FileSink {
+    writer: BufWriter<File>,
+}
+
+pub struct StdoutSink;
+
+/// Keeps the trace in memory. Clones share the same buffer, so a clone can
+/// be handed to the `Logger` and the original read after the run.
+#[derive(Clone, Default)]
+pub struct MemorySink {
+    buffer: Rc<RefCell<String>>,
+}
+
+/// Discards everything.
+pub struct NullSink;
+
+impl FileSink {
+    /// Creates the parent directories of `path` if they are missing.
+    pub fn create<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let path = path.as_ref();
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let writer = BufWriter::new(File::create(path)?);
+        Ok(FileSink { writer })
+    }
+}
+
+impl LogSink for FileSink {
+    fn write_line(&mut self, line: &str) -> io::Result<()> {
+        writeln!(self.writer, "{line}")
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+}
+
+impl LogSink for StdoutSink {
+    fn write_line(&mut self, line: &str) -> io::Result<()> {
+        writeln!(io::stdout().lock(), "{line}")
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        io::stdout().flush()
+    }
+}
+
+impl MemorySink {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn contents(&self) -> String {
+        self.buffer.borrow().clone()
+    }
+}
+
+impl LogSink for MemorySink {
+    fn write_line(&mut self, line: &str) -> io::Result<()> {
+        let mut buffer = self.buffer.borrow_mut();
+        buffer.push_str(line);
+        buffer.push('\n');
+        Ok(())
+    }
+}
+
+impl LogSink for NullSink {
+    fn write_line(&mut self, _line: &str) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::trace::TraceFormat;
+    use crate::Logger;
+
+    struct Full;
+
+    impl LogSink for Full {
+        fn write_line(&mut self, _line: &str) -> io::Result<()> {
+            Err(io::Error::new(io::ErrorKind::StorageFull, "disk full"))
+        }
+    }
+
+    #[test]
+    fn file_sink_creates_directories() {
+        let path = "target/logs/sink_test/nested/trace.log";
+        let _ = fs::remove_dir_all("target/logs/sink_test");
+        let mut sink = FileSink::create(path).unwrap();
+        sink.write_line("Iteration nr: 0").unwrap();
+        sink.flush().unwrap();
+        assert_eq!(fs::read_to_string(path).unwrap(), "Iteration nr: 0\n");
+    }
+
+    #[test]
+    fn logger_returns_first_error_on_flush() {
+        let mut logger = Logger::with_sink(Box::new(Full), TraceFormat::Text);
+        logger.log_round(0);
+        logger.log_round(1);
+        let error = logger.flush().unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::StorageFull);
+        assert!(logger.flush().is_ok());
+    }
+}
@@ -0,0 +1,219 @@
+use crate::network::VectorClock;
+use std::fmt::Debug;
+
+/// Anything that takes actions in a simulation, nodes as well as the
+/// network.
+pub trait Actor: Debug {
+    /// E.g. `Server` or `Network`.
+    fn actor_kind(&self) -> &'static str;
+    /// `None` for actors that are not a node.
+    fn actor_id(&self) -> Option<usize>;
+}
+
+impl<A: Actor + ?Sized> Actor for Box<A> {
+    fn actor_kind(&self) -> &'static str {
+        (**self).actor_kind()
+    }
+
+    fn actor_id(&self) -> Option<usize> {
+        (**self).actor_id()
+    }
+}
+
+/// An action that can be written to a structured trace. The `Debug` output
+/// stays the line of the text format.
+pub trait Traceable: Debug {
+    /// E.g. `send` or `store`.
+    fn action(&self) -> &'static str;
+    fn fields(&self) -> Vec<(&'static str, Field)>;
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Field {
+    Int(usize),
+    Bool(bool),
+    Text(String),
+    Clock(VectorClock),
+}
+
+/// How the `Logger` writes a trace.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TraceFormat {
+    /// Indented lines of rounds, actors and actions.
+    Text,
+    /// One JSON object per action.
+    JsonLines,
+}
+
+/// A single action together with the round and actor it belongs to.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TraceEvent {
+    pub round: usize,
+    pub actor: String,
+    pub actor_id: Option<usize>,
+    pub action: &'static str,
+    pub fields: Vec<(&'static str, Field)>,
+    /// The line of the text format.
+    pub text: String,
+}
+
+impl From<usize> for Field {
+    fn from(value: usize) -> Self {
+        Field::Int(value)
+    }
+}
+
+impl From<bool> for Field {
+    fn from(value: bool) -> Self {
+        Field::Bool(value)
+    }
+}
+
+impl From<String> for Field {
+    fn from(value: String) -> Self {
+        Field::Text(value)
+    }
+}
+
+impl From<&str> for Field {
+    fn from(value: &str) -> Self {
+        Field::Text(String::from(value))
+    }
+}
+
+impl Field {
+    /// Formats `value` with `Debug`, as messages and commands are written.
+    pub fn debug<T: Debug + ?Sized>(value: &T) -> Self {
+        Field::Text(format!("{value:?}"))
+    }
+
+    pub fn to_json(&self) -> String {
+        match self {
+            Field::Int(value) => value.to_string(),
+            Field::Bool(value) => value.to_string(),
+            Field::Text(value) => json_string(value),
+            Field::Clock(clock) => clock.to_json(|node| node.to_string()),
+        }
+    }
+}
+
+impl TraceEvent {
+    pub fn new<A: Traceable + ?Sized>(
+        round: usize,
+        actor: Option<(&str, Option<usize>)>,
+        action: &A,
+    ) -> Self {
+        let (actor, actor_id) = actor.unwrap_or(("", None));
+        TraceEvent {
+            round,
+            actor: String::from(actor),
+            actor_id,
+            action: action.action(),
+            fields: action.fields(),
+            text: format!("{action:?}"),
+        }
+    }
+
+    pub fn field(&self, name: &str) -> Option<&Field> {
+        self.fields.iter().find(|(n, _)| *n == name).map(|(_, f)| f)
+    }
+
+    /// A single line without the trailing newline. Fields follow the fixed
+    /// keys `round`, `actor`, `actor_id` and `action`.
+    pub fn to_json(&self) -> String {
+        let actor_id = match self.actor_id {
+            Some(id) => id.to_string(),
+            None => String::from("null"),
+        };
+        let mut json = format!(
+            "{{\"round\":{},\"actor\":{},\"actor_id\":{},\"action\":{}",
+            self.round,
+            json_string(&self.actor),
+            actor_id,
+            json_string(self.action)
+        );
+        for (name, field) in &self.fields {
+            json += &format!(",{}:{}", json_string(name), field.to_json());
+        }
+        json + "}"
+    }
+}
+
+/// Quotes and escapes `value` as a JSON string.
+pub fn json_string(value: &str) -> String {
+    let mut json = String::from("\"");
+    for c in value.chars() {
+        match c {
+            '"' => json.push_str("\\\""),
+            '\\' => json.push_str("\\\\"),
+            '\n' => json.push_str("\\n"),
+            '\t' => json.push_str("\\t"),
+            c if (c as u32) < 0x20 => json.push_str(&format!("\\u{:04x}", c as u32)),
+            c => json.push(c),
+        }
+    }
+    json.push('"');
+    json
+}
+
+/// Line number and the two differing lines of the first difference between
+/// two traces, `None` for a line after the end of one of them.
+pub fn first_difference<'a>(
+    recorded: &'a str,
+    replayed: &'a str,
+) -> Option<(usize, Option<&'a str>, Option<&'a str>)> {
+    let (mut recorded, mut replayed) = (recorded.lines(), replayed.lines());
+    let mut line = 1;
+    loop {
+        match (recorded.next(), replayed.next()) {
+            (None, None) => return None,
+            (a, b) if a != b => return Some((line, a, b)),
+            _ => line += 1,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[derive(Debug)]
+    struct Send(usize, &'static str);
+
+    impl Traceable for Send {
+        fn action(&self) -> &'static str {
+            "send"
+        }
+
+        fn fields(&self) -> Vec<(&'static str, Field)> {
+            vec![("receiver", self.0.into()), ("message", self.1.into())]
+        }
+    }
+
+    #[test]
+    fn events_are_written_as_json() {
+        let event = TraceEvent::new(3, Some(("Server", Some(1))), &Send(2, "Ok(\"a\")"));
+        assert_eq!(event.field("receiver"), Some(&Field::Int(2)));
+        assert_eq!(
+            event.to_json(),
+            "{\"round\":3,\"actor\":\"Server\",\"actor_id\":1,\"action\":\"send\",\
+             \"receiver\":2,\"message\":\"Ok(\\\"a\\\")\"}"
+        );
+        let event = TraceEvent::new(0, None, &Send(0, "\t⊥\u{1}"));
+        assert!(event.to_json().contains("\"actor_id\":null"));
+        assert!(event.to_json().ends_with("\"\\t⊥\\u0001\"}"));
+    }
+
+    #[test]
+    fn finds_first_difference() {
+        assert_eq!(first_difference("a\nb\n", "a\nb\n"), None);
+        assert_eq!(
+            first_difference("a\nb\n", "a\nc\n"),
+            Some((2, Some("b"), Some("c")))
+        );
+        assert_eq!(
+            first_difference("a\n", "a\nb\n"),
+            Some((2, None, Some("b")))
+        );
+    }
+}